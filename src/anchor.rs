@@ -0,0 +1,146 @@
+use std::collections::{HashMap, HashSet};
+
+/// Turns heading text into an anchor slug.
+///
+/// Letters and digits of any script are kept (lowercased), whitespace, `-`
+/// and `_` become a single `-`, everything else is dropped.
+///
+/// ```
+/// assert_eq!(notion_to_jarkup::anchor::slugify("Getting Started!"), "getting-started");
+/// assert_eq!(notion_to_jarkup::anchor::slugify("インストール 手順"), "インストール-手順");
+/// ```
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            slug.extend(c.to_lowercase());
+        } else if (c.is_whitespace() || c == '-' || c == '_') && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    slug.trim_matches('-').to_owned()
+}
+
+/// Hands out unique slugs, suffixing duplicates with `-1`, `-2`, ...
+#[derive(Debug, Default, Clone)]
+pub struct Slugger {
+    used: HashSet<String>,
+}
+
+impl Slugger {
+    /// Returns a slug for `text` that has not been returned before.
+    /// Falls back to `fallback` when `text` has no letters or digits.
+    pub fn slug(&mut self, text: &str, fallback: &str) -> String {
+        let base = match slugify(text) {
+            slug if slug.is_empty() => fallback.to_owned(),
+            slug => slug,
+        };
+
+        let mut candidate = base.clone();
+        let mut suffix = 0;
+
+        while self.used.contains(&candidate) {
+            suffix += 1;
+            candidate = format!("{base}-{suffix}");
+        }
+
+        self.used.insert(candidate.clone());
+        candidate
+    }
+}
+
+/// Replaces the block id of every heading, and of every toggle listed in
+/// `toggle_headings`, with a slug of its text, in document order. Returns a
+/// map from block id to slug.
+pub(crate) fn assign_heading_slugs(
+    components: &mut [jarkup_rs::Component],
+    toggle_headings: &HashMap<String, jarkup_rs::HeadingLevel>,
+) -> HashMap<String, String> {
+    let mut slugger = Slugger::default();
+    let mut anchors: HashMap<String, String> = HashMap::new();
+
+    crate::visit::walk_mut(
+        components,
+        &mut |component| {
+            let (id, text) = match component {
+                jarkup_rs::Component::BlockComponent(jarkup_rs::BlockComponent::Heading(
+                    heading,
+                )) => (&mut heading.id, &heading.slots.default),
+                jarkup_rs::Component::BlockComponent(jarkup_rs::BlockComponent::Toggle(toggle))
+                    if toggle
+                        .id
+                        .as_ref()
+                        .is_some_and(|id| toggle_headings.contains_key(id)) =>
+                {
+                    (&mut toggle.id, &toggle.slots.summary)
+                }
+                _ => return,
+            };
+
+            if let Some(block_id) = id.take() {
+                let text = crate::visit::plain_text(text);
+                let slug = slugger.slug(&text, &normalize_block_id(&block_id));
+                *id = Some(slug.clone());
+                anchors.insert(block_id, slug);
+            }
+        },
+        &mut |_| {},
+    );
+
+    anchors
+}
+
+/// Rewrites links to Notion blocks in `anchors` (such as
+/// `https://www.notion.so/Page-<page id>#<block id>`) to `#<anchor>`.
+pub(crate) fn rewrite_block_links(
+    components: &mut [jarkup_rs::Component],
+    anchors: &HashMap<String, String>,
+) {
+    let anchors_by_normalized_id: HashMap<String, &String> = anchors
+        .iter()
+        .map(|(block_id, anchor)| (normalize_block_id(block_id), anchor))
+        .collect();
+
+    crate::visit::walk_mut(components, &mut |_| {}, &mut |inline_component| {
+        if let jarkup_rs::InlineComponent::Text(text) = inline_component
+            && let Some(href) = &text.props.href
+            && let Some(block_id) = notion_link_block_id(href)
+            && let Some(anchor) = anchors_by_normalized_id.get(&block_id)
+        {
+            text.props.href = Some(format!("#{anchor}"));
+        }
+    });
+}
+
+/// Lowercase block id without dashes, as used in Notion URL fragments.
+fn normalize_block_id(block_id: &str) -> String {
+    block_id.replace('-', "").to_ascii_lowercase()
+}
+
+/// Extracts the block id from the fragment of a notion.so / notion.site link,
+/// or of a workspace-relative link such as `/<page id>#<block id>`.
+fn notion_link_block_id(href: &str) -> Option<String> {
+    let base = url::Url::parse("https://www.notion.so").ok()?;
+    let url = base.join(href).ok()?;
+
+    let host = url.host_str()?;
+    let is_notion_host = host == "notion.so"
+        || host.ends_with(".notion.so")
+        || host == "notion.site"
+        || host.ends_with(".notion.site");
+
+    if !is_notion_host {
+        return None;
+    }
+
+    let fragment = url.fragment()?;
+    let block_id = normalize_block_id(fragment.strip_prefix("block-").unwrap_or(fragment));
+
+    if block_id.len() == 32 && block_id.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(block_id)
+    } else {
+        None
+    }
+}
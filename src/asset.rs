@@ -0,0 +1,168 @@
+use sha2::Digest;
+
+/// A Notion-hosted file downloaded during conversion.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct Asset<'a> {
+    /// The image or file block the asset belongs to.
+    pub block_id: &'a str,
+
+    /// The signed Notion URL, which expires after about an hour.
+    pub url: &'a str,
+
+    /// The `Content-Type` of the download, without parameters.
+    pub content_type: Option<&'a str>,
+
+    pub bytes: &'a [u8],
+}
+
+impl<'a> Asset<'a> {
+    pub fn new(
+        block_id: &'a str,
+        url: &'a str,
+        content_type: Option<&'a str>,
+        bytes: &'a [u8],
+    ) -> Self {
+        Self {
+            block_id,
+            url,
+            content_type,
+            bytes,
+        }
+    }
+
+    /// The file extension, taken from the URL path or else the content type.
+    pub fn extension(&self) -> Option<String> {
+        let from_path = url::Url::parse(self.url).ok().and_then(|url| {
+            let file_name = url.path_segments()?.next_back()?.to_owned();
+            let (_, extension) = file_name.rsplit_once('.')?;
+
+            (!extension.is_empty()
+                && extension.len() <= 8
+                && extension.chars().all(|c| c.is_ascii_alphanumeric()))
+            .then(|| extension.to_ascii_lowercase())
+        });
+
+        from_path.or_else(|| {
+            let extension = match self.content_type? {
+                "image/png" => "png",
+                "image/jpeg" => "jpg",
+                "image/gif" => "gif",
+                "image/webp" => "webp",
+                "image/svg+xml" => "svg",
+                "image/avif" => "avif",
+                "application/pdf" => "pdf",
+                _ => return None,
+            };
+            Some(extension.to_owned())
+        })
+    }
+
+    /// The hex SHA-256 of the content.
+    pub fn content_hash(&self) -> String {
+        format!("{:x}", sha2::Sha256::digest(self.bytes))
+    }
+}
+
+/// Keeps Notion-hosted files at a stable URL. Set with
+/// [`crate::client::ClientBuilder::asset_store`].
+#[async_trait::async_trait]
+pub trait AssetStore: std::fmt::Debug + Send + Sync {
+    /// Stores the asset and returns the URL it is served from.
+    async fn store(&self, asset: Asset<'_>) -> Result<String, crate::error::Error>;
+
+    /// The URL stored assets are served under, if there is one. Subtrees in
+    /// the [`crate::cache::BlockCache`] are converted again when it changes.
+    fn public_base_url(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Writes assets to a directory under content-addressed names
+/// (`<sha256>.<extension>`), so an unchanged file is written once and keeps
+/// its URL across builds.
+///
+/// ```
+/// let store = notion_to_jarkup::asset::FilesystemAssetStore::new("public/assets", "/assets");
+/// ```
+#[derive(Debug, Clone)]
+pub struct FilesystemAssetStore {
+    directory: std::path::PathBuf,
+    public_base_url: String,
+}
+
+impl FilesystemAssetStore {
+    /// `public_base_url` is the URL `directory` is served from.
+    pub fn new(
+        directory: impl Into<std::path::PathBuf>,
+        public_base_url: impl Into<String>,
+    ) -> Self {
+        Self {
+            directory: directory.into(),
+            public_base_url: public_base_url.into(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AssetStore for FilesystemAssetStore {
+    async fn store(&self, asset: Asset<'_>) -> Result<String, crate::error::Error> {
+        let file_name = match asset.extension() {
+            Some(extension) => format!("{}.{}", asset.content_hash(), extension),
+            None => asset.content_hash(),
+        };
+
+        let path = self.directory.join(&file_name);
+
+        if !tokio::fs::try_exists(&path).await? {
+            tokio::fs::create_dir_all(&self.directory).await?;
+            write_file_atomically(&path, asset.bytes).await?;
+        }
+
+        Ok(format!(
+            "{}/{}",
+            self.public_base_url.trim_end_matches('/'),
+            file_name
+        ))
+    }
+
+    fn public_base_url(&self) -> Option<&str> {
+        Some(&self.public_base_url)
+    }
+}
+
+/// Writes to a temporary file next to `path` and renames it, so that a partial
+/// file is never read. The temporary name is unique per call, so concurrent
+/// writes of the same content do not collide; if another call renamed its file
+/// into place first, that one is kept.
+pub(crate) async fn write_file_atomically(
+    path: &std::path::Path,
+    bytes: &[u8],
+) -> Result<(), crate::error::Error> {
+    static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    let file_name = path
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let temporary_path = path.with_file_name(format!(
+        ".{file_name}.{}.{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+    ));
+
+    tokio::fs::write(&temporary_path, bytes).await?;
+
+    match tokio::fs::rename(&temporary_path, path).await {
+        Ok(()) => Ok(()),
+        Err(error) => {
+            let _ = tokio::fs::remove_file(&temporary_path).await;
+
+            if tokio::fs::try_exists(path).await.unwrap_or(false) {
+                Ok(())
+            } else {
+                Err(error.into())
+            }
+        }
+    }
+}
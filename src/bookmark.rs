@@ -0,0 +1,100 @@
+/// Metadata scraped from the page a bookmark block points to.
+///
+/// `jarkup_rs::BookmarkProps` only carries `title`, `description` and `image`;
+/// the full metadata of every bookmark is returned in
+/// [`crate::output::ConversionOutput::bookmarks`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct BookmarkMetadata {
+    /// The URL of the page after following redirects.
+    pub url: String,
+
+    /// `<link rel="canonical">` or `og:url`, resolved to an absolute URL.
+    pub canonical_url: Option<String>,
+
+    /// `og:title`, `twitter:title` or `<title>`, falling back to `site_name`.
+    pub title: Option<String>,
+
+    /// `og:description`, `twitter:description` or `<meta name="description">`.
+    pub description: Option<String>,
+
+    /// `og:image` or `twitter:image`, resolved to an absolute URL.
+    pub image: Option<String>,
+
+    /// `og:site_name`.
+    pub site_name: Option<String>,
+
+    /// The best `<link rel="icon">` or `apple-touch-icon`, resolved to an absolute URL.
+    /// When fetched through the client, falls back to `/favicon.ico` and may be a data URL.
+    pub favicon: Option<String>,
+}
+
+impl BookmarkMetadata {
+    /// Extracts the metadata from an HTML document.
+    ///
+    /// Relative URLs are resolved against `<base href>` or `url`, which should be
+    /// the final URL of the response (after redirects).
+    pub fn from_html(html: &str, url: &url::Url) -> Self {
+        let meta_scraper = html_meta_scraper::MetaScraper::new(html);
+        let document = scraper::Html::parse_document(html);
+        let base_url = crate::favicon::base_url(&document, url);
+
+        let site_name = Self::select_attr(&document, "meta[property='og:site_name']", "content");
+
+        let canonical_url = Self::select_attr(&document, "link[rel='canonical']", "href")
+            .or_else(|| Self::select_attr(&document, "meta[property='og:url']", "content"))
+            .and_then(|href| Self::resolve_url(&base_url, &href));
+
+        let title = meta_scraper
+            .title()
+            .and_then(|t| Self::non_empty(&t))
+            .or_else(|| site_name.clone());
+
+        let description = meta_scraper.description().and_then(|d| Self::non_empty(&d));
+
+        let image = meta_scraper
+            .image()
+            .and_then(|src| Self::resolve_url(&base_url, &src));
+
+        let favicon =
+            crate::favicon::find_best_icon(&document, &base_url).map(|icon| icon.to_string());
+
+        BookmarkMetadata {
+            url: url.to_string(),
+            canonical_url,
+            title,
+            description,
+            image,
+            site_name,
+            favicon,
+        }
+    }
+
+    fn select_attr(document: &scraper::Html, selector: &str, attr: &str) -> Option<String> {
+        let selector = scraper::Selector::parse(selector).ok()?;
+
+        document
+            .select(&selector)
+            .filter_map(|element| element.value().attr(attr))
+            .find_map(Self::non_empty)
+    }
+
+    fn non_empty(value: &str) -> Option<String> {
+        let trimmed = value.trim();
+
+        if trimmed.is_empty() {
+            None
+        } else {
+            Some(trimmed.to_owned())
+        }
+    }
+
+    /// Resolves `href` against `base`, keeping only `http(s)` and `data` URLs.
+    pub(crate) fn resolve_url(base: &url::Url, href: &str) -> Option<String> {
+        let resolved = base.join(href.trim()).ok()?;
+
+        match resolved.scheme() {
+            "http" | "https" | "data" => Some(resolved.to_string()),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+/// The converted children of a block, cached while the block had
+/// `last_edited_time`.
+///
+/// Besides the components, an entry keeps what the conversion recorded for the
+/// blocks below, such as image analysis results and report entries, so that a
+/// reused subtree contributes to the output as if it had been converted again.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[non_exhaustive]
+pub struct CachedSubtree {
+    pub last_edited_time: String,
+
+    /// Identifies the options and crate version the subtree was converted with.
+    pub(crate) fingerprint: String,
+
+    pub(crate) components: Vec<jarkup_rs::Component>,
+
+    /// Ids of all blocks below the block.
+    pub(crate) block_ids: Vec<String>,
+
+    #[serde(default)]
+    pub(crate) list_starts: HashMap<String, crate::list::ListStart>,
+
+    #[serde(default)]
+    pub(crate) images: HashMap<String, crate::image::ImageInfo>,
+
+    #[serde(default)]
+    pub(crate) toggle_headings: HashMap<String, jarkup_rs::HeadingLevel>,
+
+    #[serde(default)]
+    pub(crate) bookmarks: HashMap<String, crate::bookmark::BookmarkMetadata>,
+
+    #[serde(default)]
+    pub(crate) unsupported_blocks: Vec<crate::report::UnsupportedBlock>,
+
+    #[serde(default)]
+    pub(crate) dropped_mentions: Vec<crate::report::DroppedMention>,
+
+    #[serde(default)]
+    pub(crate) truncations: Vec<crate::report::Truncation>,
+}
+
+/// Identifies what a converted subtree depends on besides its blocks: the
+/// options that affect the output, where stored assets are served from,
+/// whether alt text is generated, and the crate version.
+pub(crate) fn fingerprint(
+    options: &crate::options::ConversionOptions,
+    asset_base_url: Option<&str>,
+    generates_alt_text: bool,
+) -> Result<String, crate::error::Error> {
+    use sha2::Digest;
+
+    let mut options = serde_json::to_value(options)?;
+
+    // Throttling, budgets and concurrency change how a page is fetched, not
+    // what it converts to.
+    if let Some(options) = options.as_object_mut() {
+        options.remove("rate_limit");
+
+        for (group, key) in [
+            ("limits", "timeout_ms"),
+            ("limits", "max_api_calls"),
+            ("limits", "max_outbound_requests"),
+            ("limits", "on_exceeded"),
+            ("network", "concurrency_limit"),
+            ("network", "user_agent"),
+            ("network", "max_asset_bytes"),
+        ] {
+            if let Some(group) = options
+                .get_mut(group)
+                .and_then(serde_json::Value::as_object_mut)
+            {
+                group.remove(key);
+            }
+        }
+    }
+
+    let value = serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "options": options,
+        "asset_base_url": asset_base_url,
+        "generates_alt_text": generates_alt_text,
+    });
+
+    Ok(format!(
+        "{:x}",
+        sha2::Sha256::digest(serde_json::to_vec(&value)?)
+    ))
+}
+
+/// Keeps converted subtrees between conversions. Set with
+/// [`crate::client::ClientBuilder::block_cache`].
+///
+/// Every conversion stores the converted children of each block that has
+/// children. [`crate::client::Client::convert`] looks a block up before listing
+/// its children; if it was cached while the block had the same
+/// `last_edited_time`, with the same output options, the cached components are used
+/// and nothing below the block is fetched. The children of the page itself are
+/// always listed, since the page's own edit time is not fetched.
+///
+/// A cached subtree is only refreshed when the edit time of its block changes,
+/// so an edit deep inside it is not picked up until then.
+///
+/// Errors are not fatal: a failed `get` is treated as a miss and a failed
+/// `put` leaves the block uncached. Both are recorded in
+/// [`crate::report::ConversionReport::cache_failures`].
+#[async_trait::async_trait]
+pub trait BlockCache: std::fmt::Debug + Send + Sync {
+    async fn get(&self, block_id: &str) -> Result<Option<CachedSubtree>, crate::error::Error>;
+
+    async fn put(&self, block_id: &str, subtree: &CachedSubtree)
+    -> Result<(), crate::error::Error>;
+}
+
+/// Keeps subtrees in memory, for the lifetime of the cache.
+#[derive(Debug, Default)]
+pub struct MemoryBlockCache {
+    entries: std::sync::Mutex<HashMap<String, serde_json::Value>>,
+}
+
+impl MemoryBlockCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl BlockCache for MemoryBlockCache {
+    async fn get(&self, block_id: &str) -> Result<Option<CachedSubtree>, crate::error::Error> {
+        let value = self
+            .entries
+            .lock()
+            .expect("entries lock poisoned")
+            .get(&crate::source::normalize_id(block_id))
+            .cloned();
+
+        Ok(value.map(serde_json::from_value).transpose()?)
+    }
+
+    async fn put(
+        &self,
+        block_id: &str,
+        subtree: &CachedSubtree,
+    ) -> Result<(), crate::error::Error> {
+        let value = serde_json::to_value(subtree)?;
+
+        self.entries
+            .lock()
+            .expect("entries lock poisoned")
+            .insert(crate::source::normalize_id(block_id), value);
+
+        Ok(())
+    }
+}
+
+/// Keeps subtrees as `<block id>.json` files in a directory, so they survive
+/// across builds.
+///
+/// ```
+/// let cache = notion_to_jarkup::cache::FilesystemBlockCache::new(".cache/notion");
+/// ```
+#[derive(Debug, Clone)]
+pub struct FilesystemBlockCache {
+    directory: std::path::PathBuf,
+}
+
+impl FilesystemBlockCache {
+    pub fn new(directory: impl Into<std::path::PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    fn path(&self, block_id: &str) -> std::path::PathBuf {
+        self.directory
+            .join(format!("{}.json", crate::source::normalize_id(block_id)))
+    }
+}
+
+#[async_trait::async_trait]
+impl BlockCache for FilesystemBlockCache {
+    async fn get(&self, block_id: &str) -> Result<Option<CachedSubtree>, crate::error::Error> {
+        match tokio::fs::read(self.path(block_id)).await {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    async fn put(
+        &self,
+        block_id: &str,
+        subtree: &CachedSubtree,
+    ) -> Result<(), crate::error::Error> {
+        let bytes = serde_json::to_vec(subtree)?;
+
+        tokio::fs::create_dir_all(&self.directory).await?;
+        crate::asset::write_file_atomically(&self.path(block_id), &bytes).await
+    }
+}
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Stops a conversion started with
+/// [`crate::client::Client::convert_cancellable`] when [`cancel`](Self::cancel)
+/// is called or, if set, when its deadline passes.
+///
+/// Clones share the same state, so a clone can be cancelled from another task.
+///
+/// ```
+/// let token = notion_to_jarkup::cancel::CancellationToken::with_deadline(
+///     std::time::Instant::now() + std::time::Duration::from_secs(5),
+/// );
+///
+/// let handle = token.clone();
+/// handle.cancel();
+/// assert!(token.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+    deadline: Option<std::time::Instant>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    notify: tokio::sync::Notify,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token that also stops the conversion at `deadline`.
+    pub fn with_deadline(deadline: std::time::Instant) -> Self {
+        Self {
+            inner: Arc::default(),
+            deadline: Some(deadline),
+        }
+    }
+
+    pub fn deadline(&self) -> Option<std::time::Instant> {
+        self.deadline
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    /// Whether [`cancel`](Self::cancel) was called. The deadline is not considered.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Completes when [`cancel`](Self::cancel) is called.
+    pub async fn cancelled(&self) {
+        loop {
+            let mut notified = std::pin::pin!(self.inner.notify.notified());
+            // Register before checking the flag, so a concurrent `cancel` is not missed.
+            notified.as_mut().enable();
+
+            if self.is_cancelled() {
+                return;
+            }
+
+            notified.await;
+        }
+    }
+}
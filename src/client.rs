@@ -1,50 +1,182 @@
-use futures::TryStreamExt;
 use futures::future::try_join_all;
-use notionrs::PaginateExt;
+use futures::{StreamExt, TryStreamExt};
 use notionrs_types::prelude::*;
-use std::collections::HashMap;
 
 #[derive(Debug)]
 pub struct Client {
-    pub notionrs_client: notionrs::client::Client,
-    pub reqwest_client: reqwest::Client,
+    pub(crate) source: std::sync::Arc<dyn crate::source::BlockSource>,
+    pub(crate) reqwest_client: reqwest::Client,
+    pub(crate) options: crate::options::ConversionOptions,
+    pub(crate) image_alt_text: Option<crate::image::ImageAltText>,
+    pub(crate) image_fetcher: std::sync::Arc<dyn crate::image::ImageFetcher>,
+    pub(crate) asset_store: Option<std::sync::Arc<dyn crate::asset::AssetStore>>,
+    pub(crate) block_cache: Option<std::sync::Arc<dyn crate::cache::BlockCache>>,
+    pub(crate) cache_fingerprint: String,
+}
 
-    /// If true, unsupported blocks will be rendered as `Unsupported` blocks.
-    /// If false, unsupported blocks will be skipped.
-    pub enable_unsupported_block: bool,
+/// Builder for [`Client`], created with [`Client::builder`].
+#[derive(Debug, Default)]
+pub struct ClientBuilder {
+    source: Option<std::sync::Arc<dyn crate::source::BlockSource>>,
+    reqwest_client: Option<reqwest::Client>,
+    options: crate::options::ConversionOptions,
+    image_alt_text: Option<crate::image::ImageAltText>,
+    image_fetcher: Option<std::sync::Arc<dyn crate::image::ImageFetcher>>,
+    asset_store: Option<std::sync::Arc<dyn crate::asset::AssetStore>>,
+    block_cache: Option<std::sync::Arc<dyn crate::cache::BlockCache>>,
+}
+
+impl ClientBuilder {
+    /// The client used to call the Notion API.
+    ///
+    /// Either this or [`ClientBuilder::block_source`] is required to fetch
+    /// anything. Without one, only [`Client::convert_tree`] works; every other
+    /// conversion fails with [`crate::error::Error::Builder`].
+    pub fn notionrs_client(mut self, notionrs_client: notionrs::client::Client) -> Self {
+        self.source = Some(std::sync::Arc::new(notionrs_client));
+        self
+    }
+
+    /// Reads Notion data from somewhere other than the Notion API, such as
+    /// [`crate::source::DirectorySource`] or [`crate::source::MemorySource`].
+    pub fn block_source(mut self, source: impl crate::source::BlockSource + 'static) -> Self {
+        self.source = Some(std::sync::Arc::new(source));
+        self
+    }
+
+    /// The client used to fetch bookmarks and favicons.
+    /// Defaults to `reqwest::Client::new()`.
+    pub fn reqwest_client(mut self, reqwest_client: reqwest::Client) -> Self {
+        self.reqwest_client = Some(reqwest_client);
+        self
+    }
+
+    /// Replaces all conversion options.
+    pub fn options(mut self, options: crate::options::ConversionOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets what to do with blocks that have no jarkup counterpart.
+    pub fn unsupported_block(mut self, policy: crate::options::UnsupportedBlockPolicy) -> Self {
+        self.options.unsupported_block = policy;
+        self
+    }
+
+    /// Supplies alt text for images, given the block id and the image URL.
+    /// Alt text returned here overrides the caption; on `None` the plain
+    /// caption text is used, or an empty `alt` without a caption.
+    pub fn image_alt_text<F>(mut self, image_alt_text: F) -> Self
+    where
+        F: Fn(&str, &str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.image_alt_text = Some(crate::image::ImageAltText::new(image_alt_text));
+        self
+    }
+
+    /// Downloads images for image analysis.
+    /// Defaults to a [`crate::image::ReqwestImageFetcher`] using `reqwest_client`.
+    pub fn image_fetcher(
+        mut self,
+        image_fetcher: impl crate::image::ImageFetcher + 'static,
+    ) -> Self {
+        self.image_fetcher = Some(std::sync::Arc::new(image_fetcher));
+        self
+    }
+
+    /// Stores Notion-hosted images and files, whose URLs expire after about an
+    /// hour, and rewrites their `src` to the store's URL. Unset by default.
+    pub fn asset_store(mut self, asset_store: impl crate::asset::AssetStore + 'static) -> Self {
+        self.asset_store = Some(std::sync::Arc::new(asset_store));
+        self
+    }
+
+    /// Reuses the converted children of blocks whose `last_edited_time` is
+    /// unchanged since they were cached, instead of fetching and converting
+    /// them again. Unset by default.
+    pub fn block_cache(mut self, block_cache: impl crate::cache::BlockCache + 'static) -> Self {
+        self.block_cache = Some(std::sync::Arc::new(block_cache));
+        self
+    }
+
+    pub fn build(self) -> Result<Client, crate::error::Error> {
+        let source = self
+            .source
+            .unwrap_or_else(|| std::sync::Arc::new(crate::source::MissingSource));
+
+        let reqwest_client = self.reqwest_client.unwrap_or_default();
+
+        let image_fetcher = self.image_fetcher.unwrap_or_else(|| {
+            std::sync::Arc::new(crate::image::ReqwestImageFetcher::new(
+                reqwest_client.clone(),
+                self.options.network.user_agent.clone(),
+            ))
+        });
+
+        let source: std::sync::Arc<dyn crate::source::BlockSource> = if source.needs_rate_limit() {
+            std::sync::Arc::new(crate::rate_limit::RateLimitedSource::new(
+                source,
+                &self.options.rate_limit,
+            ))
+        } else {
+            source
+        };
+
+        let cache_fingerprint = crate::cache::fingerprint(
+            &self.options,
+            self.asset_store
+                .as_ref()
+                .map(|asset_store| asset_store.public_base_url().unwrap_or_default()),
+            self.image_alt_text.is_some(),
+        )?;
+
+        Ok(Client {
+            source,
+            reqwest_client,
+            options: self.options,
+            image_alt_text: self.image_alt_text,
+            image_fetcher,
+            asset_store: self.asset_store,
+            block_cache: self.block_cache,
+            cache_fingerprint,
+        })
+    }
 }
 
 impl Client {
-    fn map_color(color: Color) -> Option<String> {
-        match color {
-            notionrs_types::object::color::Color::Default => None,
-            notionrs_types::object::color::Color::Blue => Some(String::from("#6987b8")),
-            notionrs_types::object::color::Color::Brown => Some(String::from("#8b4c3f")),
-            notionrs_types::object::color::Color::Gray => Some(String::from("#868e9c")),
-            notionrs_types::object::color::Color::Green => Some(String::from("#59b57c")),
-            notionrs_types::object::color::Color::Orange => Some(String::from("#bf7e71")),
-            notionrs_types::object::color::Color::Pink => Some(String::from("#c9699e")),
-            notionrs_types::object::color::Color::Purple => Some(String::from("#9771bd")),
-            notionrs_types::object::color::Color::Red => Some(String::from("#b36472")),
-            notionrs_types::object::color::Color::Yellow => Some(String::from("#b8a36e")),
-            _ => None,
-        }
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    pub fn options(&self) -> &crate::options::ConversionOptions {
+        &self.options
+    }
+
+    fn render_unsupported_block(&self) -> bool {
+        self.options.unsupported_block == crate::options::UnsupportedBlockPolicy::Render
+    }
+
+    fn map_color(&self, color: Color) -> Option<String> {
+        self.options.color_theme.foreground(color)
+    }
+
+    fn map_background_color(&self, background_color: Color) -> Option<String> {
+        self.options.color_theme.background(background_color)
     }
 
-    fn map_background_color(background_color: Color) -> Option<String> {
-        match background_color {
-            notionrs_types::object::color::Color::Default => None,
-            notionrs_types::object::color::Color::BlueBackground => Some(String::from("#c0cce1")),
-            notionrs_types::object::color::Color::BrownBackground => Some(String::from("#d0bdac")),
-            notionrs_types::object::color::Color::GrayBackground => Some(String::from("#cccfd5")),
-            notionrs_types::object::color::Color::GreenBackground => Some(String::from("#b1dcc2")),
-            notionrs_types::object::color::Color::OrangeBackground => Some(String::from("#f1dbd2")),
-            notionrs_types::object::color::Color::PinkBackground => Some(String::from("#ebc7db")),
-            notionrs_types::object::color::Color::PurpleBackground => Some(String::from("#d7c8e5")),
-            notionrs_types::object::color::Color::RedBackground => Some(String::from("#e8c2c2")),
-            notionrs_types::object::color::Color::YellowBackground => Some(String::from("#f0e9d7")),
-            _ => None,
+    /// Paragraph props for a block color, or `None` if the color resolves to nothing.
+    fn map_paragraph_props(&self, color: Color) -> Option<jarkup_rs::ParagraphProps> {
+        let color_value = self.map_color(color);
+        let background_color = self.map_background_color(color);
+
+        if color_value.is_none() && background_color.is_none() {
+            return None;
         }
+
+        Some(jarkup_rs::ParagraphProps {
+            color: color_value,
+            background_color,
+        })
     }
 
     fn create_unsupported_component(&self, block_name: &str) -> jarkup_rs::Component {
@@ -58,695 +190,1334 @@ impl Client {
         .into()
     }
 
-    #[async_recursion::async_recursion]
-    pub async fn convert_block(
+    /// Records an unsupported block in the report and returns its placeholder,
+    /// if `unsupported_block` renders them.
+    fn convert_unsupported_block(
         &self,
+        context: &crate::context::ConversionContext,
         block_id: &str,
-    ) -> Result<Vec<jarkup_rs::Component>, crate::error::Error> {
-        let mut components: Vec<jarkup_rs::Component> = Vec::new();
+        block_name: &str,
+    ) -> Option<jarkup_rs::Component> {
+        context.insert_unsupported_block(block_id, block_name);
 
-        let blocks: Vec<BlockResponse> = self
-            .notionrs_client
-            .get_block_children()
-            .block_id(block_id)
-            .into_stream()
-            .try_collect()
-            .await?;
+        self.render_unsupported_block()
+            .then(|| self.create_unsupported_component(block_name))
+    }
+
+    /// Fetches `block_id` and all of its descendants.
+    ///
+    /// With `limits.on_exceeded` set to `partial`, a tree whose own children
+    /// could not be listed in time is returned with `truncated` set to
+    /// [`crate::report::TruncationReason::Stopped`].
+    pub async fn fetch_tree(
+        &self,
+        block_id: &str,
+    ) -> Result<crate::tree::BlockTree, crate::error::Error> {
+        let context = self.create_context(
+            &crate::cancel::CancellationToken::new(),
+            std::time::Instant::now(),
+        );
+
+        let block = context
+            .api_call(self.source.retrieve_block(block_id))
+            .await
+            .map_err(|error| error.in_block(block_id))?;
 
-        // Collect blocks that have children
-        let blocks_needing_children: Vec<&BlockResponse> =
-            blocks.iter().filter(|b| b.has_children).collect();
+        let (children, truncated) = self.fetch_page_children(&context, block_id).await?;
+
+        Ok(crate::tree::BlockTree {
+            block,
+            children,
+            children_error: None,
+            truncated,
+        })
+    }
+
+    /// Fetches the children of `block_id`, recursively.
+    ///
+    /// If the children of `block_id` itself could not be listed because the
+    /// conversion stopped, this fails with [`crate::error::Error::Stopped`]
+    /// whatever `limits.on_exceeded` is, since there is nothing to mark as
+    /// partial.
+    pub async fn fetch_children(
+        &self,
+        block_id: &str,
+    ) -> Result<Vec<crate::tree::BlockTree>, crate::error::Error> {
+        let context = self.create_context(
+            &crate::cancel::CancellationToken::new(),
+            std::time::Instant::now(),
+        );
+
+        match self.fetch_page_children(&context, block_id).await? {
+            (_, Some(crate::report::TruncationReason::Stopped)) => {
+                let reason = context
+                    .recorded_stop_reason()
+                    .expect("a stopped listing records why");
+                Err(crate::error::Error::Stopped(reason).in_block(block_id))
+            }
+            (children, _) => Ok(children),
+        }
+    }
+
+    fn create_context(
+        &self,
+        cancellation: &crate::cancel::CancellationToken,
+        started: std::time::Instant,
+    ) -> crate::context::ConversionContext {
+        crate::context::ConversionContext::new(&self.options, cancellation, started)
+    }
+
+    fn returns_partial_output(&self) -> bool {
+        self.options.limits.on_exceeded == crate::options::LimitPolicy::Partial
+    }
+
+    /// Fetches the children of the page (or block) a conversion starts from.
+    /// If they could not be listed before the conversion stopped and partial
+    /// output is allowed, returns no children and why.
+    async fn fetch_page_children(
+        &self,
+        context: &crate::context::ConversionContext,
+        block_id: &str,
+    ) -> Result<
+        (
+            Vec<crate::tree::BlockTree>,
+            Option<crate::report::TruncationReason>,
+        ),
+        crate::error::Error,
+    > {
+        context.visit(block_id);
+
+        match self.fetch_children_in(context, block_id, 1).await {
+            Ok(children) => Ok((children, None)),
+            Err(error) if error.is_stopped() && self.returns_partial_output() => {
+                Ok((Vec::new(), Some(crate::report::TruncationReason::Stopped)))
+            }
+            Err(error) => Err(error.in_block(block_id)),
+        }
+    }
+
+    /// Fetches the children of `block_id`, which are at `depth`, recursively.
+    /// API calls and visited blocks are tracked in `context`.
+    #[async_recursion::async_recursion]
+    async fn fetch_children_in(
+        &self,
+        context: &crate::context::ConversionContext,
+        block_id: &str,
+        depth: usize,
+    ) -> Result<Vec<crate::tree::BlockTree>, crate::error::Error> {
+        let blocks = self.list_children(context, block_id).await?;
 
         // Fetch all children concurrently
-        let child_results = try_join_all(
-            blocks_needing_children
-                .iter()
-                .map(|b| self.convert_block(&b.id)),
-        )
-        .await?;
+        let children = try_join_all(blocks.iter().map(|b| async move {
+            let is_first_visit = context.visit(&b.id);
 
-        // Build a lookup map: block_id -> children components
-        let mut children_cache: HashMap<String, Vec<jarkup_rs::Component>> =
-            blocks_needing_children
-                .iter()
-                .zip(child_results)
-                .map(|(b, children)| (b.id.clone(), children))
-                .collect();
-
-        for block in blocks {
-            match block.block {
-                notionrs_types::object::block::Block::Audio { audio: _ } => {
-                    if self.enable_unsupported_block {
-                        components.push(self.create_unsupported_component("Audio"));
-                    } else {
-                        continue;
-                    }
-                }
-                notionrs_types::object::block::Block::Bookmark { bookmark } => {
-                    let html = self
-                        .reqwest_client
-                        .get(&bookmark.url)
-                        .header("user-agent", "notion-to-jarkup")
-                        .send()
-                        .await?
-                        .text()
-                        .await?;
+            if !b.has_children {
+                return Ok((Vec::new(), None, None));
+            }
 
-                    let meta_scraper = html_meta_scraper::MetaScraper::new(&html);
+            if !is_first_visit {
+                return Ok((
+                    Vec::new(),
+                    None,
+                    Some(crate::report::TruncationReason::Cycle),
+                ));
+            }
 
-                    let title = meta_scraper.title();
-                    let description = meta_scraper.description();
-                    let image = meta_scraper.image();
+            if self
+                .options
+                .limits
+                .max_depth
+                .is_some_and(|max_depth| depth >= max_depth)
+            {
+                return Ok((
+                    Vec::new(),
+                    None,
+                    Some(crate::report::TruncationReason::DepthLimit),
+                ));
+            }
 
-                    let component = jarkup_rs::Bookmark {
-                        id: Some(block.id),
-                        props: jarkup_rs::BookmarkProps {
-                            url: bookmark.url,
-                            title,
-                            description,
-                            image,
-                        },
-                        slots: None,
-                    };
+            if let Some(subtree) = self.get_cached_subtree(context, b).await {
+                context.insert_cached_subtree(b.id.clone(), subtree);
+                return Ok((Vec::new(), None, None));
+            }
 
-                    components.push(component.into());
-                }
-                notionrs_types::object::block::Block::Breadcrumb { breadcrumb: _ } => {
-                    if self.enable_unsupported_block {
-                        components.push(self.create_unsupported_component("Breadcrumb"));
+            match self.fetch_children_in(context, &b.id, depth + 1).await {
+                Ok(children) => Ok((children, None, None)),
+                Err(error) if error.is_stopped() => {
+                    if self.returns_partial_output() {
+                        Ok((
+                            Vec::new(),
+                            None,
+                            Some(crate::report::TruncationReason::Stopped),
+                        ))
                     } else {
-                        continue;
+                        Err(error.in_block(&b.id))
                     }
                 }
-                notionrs_types::object::block::Block::BulletedListItem { bulleted_list_item } => {
-                    let inline_components: Vec<jarkup_rs::Component> = self
-                        .convert_rich_text(bulleted_list_item.rich_text)
-                        .await?
-                        .into_iter()
-                        .map(jarkup_rs::Component::InlineComponent)
-                        .collect();
+                Err(error) if self.options.on_error != crate::options::ErrorPolicy::Fail => {
+                    Ok((Vec::new(), Some(error.to_string()), None))
+                }
+                Err(error) => Err(error.in_block(&b.id)),
+            }
+        }))
+        .await?;
 
-                    let children_components = children_cache.remove(&block.id).unwrap_or_default();
+        Ok(blocks
+            .into_iter()
+            .zip(children)
+            .map(
+                |(block, (children, children_error, truncated))| crate::tree::BlockTree {
+                    block,
+                    children,
+                    children_error,
+                    truncated,
+                },
+            )
+            .collect())
+    }
 
-                    let merged_components = inline_components
-                        .into_iter()
-                        .chain(children_components)
-                        .collect::<Vec<jarkup_rs::Component>>();
+    /// Lists the children of `block_id` one page at a time, so that every page
+    /// request counts as an API call and against `limits.max_api_calls`.
+    async fn list_children(
+        &self,
+        context: &crate::context::ConversionContext,
+        block_id: &str,
+    ) -> Result<Vec<BlockResponse>, crate::error::Error> {
+        let mut children = Vec::new();
+        let mut start_cursor = None;
+
+        loop {
+            let page = context
+                .api_call(
+                    self.source
+                        .list_block_children_page(block_id, start_cursor.as_deref()),
+                )
+                .await?;
+            children.extend(page.results);
+
+            match page.next_cursor {
+                Some(next_cursor) => start_cursor = Some(next_cursor),
+                None => return Ok(children),
+            }
+        }
+    }
 
-                    let list_item_component = jarkup_rs::ListItem {
-                        id: Some(block.id),
-                        props: None,
-                        slots: jarkup_rs::ListItemSlots {
-                            default: merged_components,
-                        },
-                    };
+    /// The converted children of `block`, if they were cached while it had
+    /// its current edit time. Cache errors are recorded and count as a miss.
+    async fn get_cached_subtree(
+        &self,
+        context: &crate::context::ConversionContext,
+        block: &BlockResponse,
+    ) -> Option<crate::cache::CachedSubtree> {
+        let block_cache = self.block_cache.as_ref()?;
 
-                    let maybe_prev_component = components.last_mut().and_then(|c| match c {
-                        jarkup_rs::Component::InlineComponent(_) => None,
-                        jarkup_rs::Component::BlockComponent(block_component) => {
-                            match block_component {
-                                jarkup_rs::BlockComponent::List(list) => Some(list),
-                                _ => None,
-                            }
-                        }
-                    });
+        if !context.reuses_cached_subtrees() {
+            return None;
+        }
 
-                    match maybe_prev_component {
-                        Some(prev_component) => {
-                            let is_unordered = prev_component
-                                .props
-                                .clone()
-                                .map(|p| {
-                                    matches!(p.list_style, Some(jarkup_rs::ListStyle::Unordered))
-                                })
-                                .unwrap_or(true);
-
-                            if is_unordered {
-                                prev_component
-                                    .slots
-                                    .default
-                                    .push(list_item_component.into());
-
-                                continue;
-                            }
-                        }
+        match block_cache.get(&block.id).await {
+            Ok(subtree) => subtree.filter(|subtree| {
+                subtree.last_edited_time == block.last_edited_time.to_string()
+                    && subtree.fingerprint == self.cache_fingerprint
+            }),
+            Err(error) => {
+                context.insert_cache_failure(
+                    &block.id,
+                    crate::report::CacheOperation::Get,
+                    &error.to_string(),
+                );
+                None
+            }
+        }
+    }
 
-                        None => {}
-                    };
+    /// Stores `components`, the converted children of `tree`, in the block
+    /// cache. Cache errors are recorded and otherwise ignored.
+    async fn put_cached_subtree(
+        &self,
+        context: &crate::context::ConversionContext,
+        tree: &crate::tree::BlockTree,
+        components: &[jarkup_rs::Component],
+    ) {
+        let Some(block_cache) = &self.block_cache else {
+            return;
+        };
 
-                    let component = jarkup_rs::List {
-                        id: None,
-                        props: Some(jarkup_rs::ListProps {
-                            list_style: Some(jarkup_rs::ListStyle::Unordered),
-                        }),
-                        slots: jarkup_rs::ListSlots {
-                            default: vec![list_item_component.into()],
-                        },
-                    };
+        if !tree.block.has_children {
+            return;
+        }
+
+        let mut block_ids = Vec::new();
+        Self::collect_block_ids(context, &tree.children, &mut block_ids);
+
+        let Some(subtree) = context.create_cached_subtree(
+            tree.block.last_edited_time.to_string(),
+            self.cache_fingerprint.clone(),
+            components.to_vec(),
+            block_ids,
+        ) else {
+            return;
+        };
 
-                    components.push(component.into());
+        if let Err(error) = block_cache.put(&tree.block.id, &subtree).await {
+            context.insert_cache_failure(
+                &tree.block.id,
+                crate::report::CacheOperation::Put,
+                &error.to_string(),
+            );
+        }
+    }
+
+    /// Collects the ids of `trees` and their descendants, including those of
+    /// subtrees reused from the block cache.
+    fn collect_block_ids(
+        context: &crate::context::ConversionContext,
+        trees: &[crate::tree::BlockTree],
+        block_ids: &mut Vec<String>,
+    ) {
+        for tree in trees {
+            block_ids.push(tree.block.id.clone());
+
+            match context.cached_subtree(&tree.block.id) {
+                Some(subtree) => block_ids.extend(subtree.block_ids.iter().cloned()),
+                None => Self::collect_block_ids(context, &tree.children, block_ids),
+            }
+        }
+    }
+
+    /// The placeholder for the children of a block that were not fetched.
+    fn create_truncation_placeholder(
+        &self,
+        block_id: &str,
+        reason: crate::report::TruncationReason,
+    ) -> jarkup_rs::Component {
+        jarkup_rs::Unsupported {
+            id: Some(block_id.to_owned()),
+            props: Some(jarkup_rs::UnsupportedProps {
+                details: format!(
+                    "Notion: children of block `{block_id}` were not converted: {reason}."
+                ),
+            }),
+            slots: None,
+        }
+        .into()
+    }
+
+    /// The components standing in for a block that failed, as set by `on_error`.
+    fn create_failure_placeholder(
+        &self,
+        block_id: &str,
+        message: &str,
+    ) -> Vec<jarkup_rs::Component> {
+        match self.options.on_error {
+            crate::options::ErrorPolicy::Skip => Vec::new(),
+            _ => vec![
+                jarkup_rs::Unsupported {
+                    id: Some(block_id.to_owned()),
+                    props: Some(jarkup_rs::UnsupportedProps {
+                        details: format!(
+                            "Notion: block `{block_id}` could not be converted: {message}"
+                        ),
+                    }),
+                    slots: None,
                 }
-                notionrs_types::object::block::Block::Callout { callout } => {
-                    let maybe_paragraph_component: Option<jarkup_rs::Component> =
-                        if callout.rich_text.len() > 0 {
-                            Some(
-                                jarkup_rs::Paragraph {
-                                    id: Some(block.id.clone()),
-                                    props: None,
-                                    slots: jarkup_rs::ParagraphSlots {
-                                        default: self.convert_rich_text(callout.rich_text).await?,
-                                    },
-                                }
-                                .into(),
-                            )
-                        } else {
-                            None
-                        };
+                .into(),
+            ],
+        }
+    }
 
-                    let maybe_children_components = children_cache.remove(&block.id);
+    /// Converts the children of `block_id` and applies the options that need
+    /// the whole page, such as heading anchors.
+    pub async fn convert(
+        &self,
+        block_id: &str,
+    ) -> Result<crate::output::ConversionOutput, crate::error::Error> {
+        self.convert_cancellable(block_id, &crate::cancel::CancellationToken::new())
+            .await
+    }
 
-                    let merged_children_components = maybe_paragraph_component
-                        .into_iter()
-                        .chain(maybe_children_components.unwrap_or_default())
-                        .collect::<Vec<jarkup_rs::Component>>();
-
-                    let component = jarkup_rs::Callout {
-                        id: Some(block.id),
-                        props: Some(jarkup_rs::CalloutProps {
-                            r#type: Some(match callout.color {
-                                notionrs_types::object::color::Color::Default
-                                | notionrs_types::object::color::Color::DefaultBackground
-                                | notionrs_types::object::color::Color::Blue
-                                | notionrs_types::object::color::Color::BlueBackground
-                                | notionrs_types::object::color::Color::Gray
-                                | notionrs_types::object::color::Color::GrayBackground => {
-                                    jarkup_rs::CalloutType::Note
-                                }
-                                notionrs_types::object::color::Color::Green
-                                | notionrs_types::object::color::Color::GreenBackground => {
-                                    jarkup_rs::CalloutType::Tip
-                                }
-                                notionrs_types::object::color::Color::Purple
-                                | notionrs_types::object::color::Color::PurpleBackground => {
-                                    jarkup_rs::CalloutType::Important
-                                }
-                                notionrs_types::object::color::Color::Yellow
-                                | notionrs_types::object::color::Color::YellowBackground
-                                | notionrs_types::object::color::Color::Orange
-                                | notionrs_types::object::color::Color::OrangeBackground
-                                | notionrs_types::object::color::Color::Brown
-                                | notionrs_types::object::color::Color::BrownBackground => {
-                                    jarkup_rs::CalloutType::Warning
-                                }
-                                notionrs_types::object::color::Color::Red
-                                | notionrs_types::object::color::Color::RedBackground
-                                | notionrs_types::object::color::Color::Pink
-                                | notionrs_types::object::color::Color::PinkBackground => {
-                                    jarkup_rs::CalloutType::Caution
-                                }
-                            }),
-                        }),
-                        slots: jarkup_rs::CalloutSlots {
-                            default: merged_children_components,
-                        },
-                    };
+    /// Like [`Client::convert`], but stops when `cancellation` is cancelled or
+    /// its deadline passes. See [`crate::options::LimitOptions`] for what is
+    /// returned then.
+    pub async fn convert_cancellable(
+        &self,
+        block_id: &str,
+        cancellation: &crate::cancel::CancellationToken,
+    ) -> Result<crate::output::ConversionOutput, crate::error::Error> {
+        let started = std::time::Instant::now();
+        let mut context = self.create_context(cancellation, started);
+        context.reuse_cached_subtrees();
+
+        let (children, _) = self.fetch_page_children(&context, block_id).await?;
 
-                    components.push(component.into());
+        self.convert_trees(&context, &children, started)
+            .await
+            .map_err(|error| error.in_block(block_id))
+    }
+
+    /// Converts the children of an already fetched tree, like [`Client::convert`].
+    ///
+    /// No Notion API calls are made. With `fetch_bookmark_metadata` and
+    /// image analysis off and no asset store, no network requests are made at all.
+    ///
+    /// If the tree itself is `truncated`, e.g. because [`Client::fetch_tree`]
+    /// stopped before listing its children, that is recorded in
+    /// `report.truncations`.
+    pub async fn convert_tree(
+        &self,
+        tree: &crate::tree::BlockTree,
+    ) -> Result<crate::output::ConversionOutput, crate::error::Error> {
+        self.convert_tree_cancellable(tree, &crate::cancel::CancellationToken::new())
+            .await
+    }
+
+    /// Like [`Client::convert_tree`], but stops when `cancellation` is cancelled
+    /// or its deadline passes.
+    pub async fn convert_tree_cancellable(
+        &self,
+        tree: &crate::tree::BlockTree,
+        cancellation: &crate::cancel::CancellationToken,
+    ) -> Result<crate::output::ConversionOutput, crate::error::Error> {
+        let started = std::time::Instant::now();
+        let context = self.create_context(cancellation, started);
+
+        if let Some(reason) = tree.truncated {
+            context.insert_truncation(&tree.block.id, reason);
+        }
+
+        self.convert_trees(&context, &tree.children, started)
+            .await
+            .map_err(|error| error.in_block(&tree.block.id))
+    }
+
+    async fn convert_trees(
+        &self,
+        context: &crate::context::ConversionContext,
+        trees: &[crate::tree::BlockTree],
+        started: std::time::Instant,
+    ) -> Result<crate::output::ConversionOutput, crate::error::Error> {
+        let mut components = self.convert_children(context, trees).await?;
+
+        let ordered_lists =
+            crate::list::describe_ordered_lists(&components, &context.take_list_starts());
+
+        let mut toggle_headings = context.take_toggle_headings();
+
+        crate::heading::adjust_heading_levels(
+            &mut components,
+            &mut toggle_headings,
+            &self.options.heading_level,
+        );
+
+        let anchors = match self.options.heading_anchor {
+            crate::options::HeadingAnchorStyle::BlockId => std::collections::HashMap::new(),
+            crate::options::HeadingAnchorStyle::Slug => {
+                let anchors =
+                    crate::anchor::assign_heading_slugs(&mut components, &toggle_headings);
+                crate::anchor::rewrite_block_links(&mut components, &anchors);
+                anchors
+            }
+        };
+
+        let incomplete = context.recorded_stop_reason();
+
+        if let Some(reason) = incomplete
+            && !self.returns_partial_output()
+        {
+            return Err(crate::error::Error::Stopped(reason));
+        }
+
+        Ok(crate::output::ConversionOutput {
+            components,
+            anchors,
+            ordered_lists,
+            images: context.take_images(),
+            bookmarks: context.take_bookmarks(),
+            toggle_headings,
+            report: crate::report::ConversionReport {
+                incomplete,
+                elapsed: started.elapsed(),
+                ..context.take_report()
+            },
+        })
+    }
+
+    pub async fn convert_block(
+        &self,
+        block_id: &str,
+    ) -> Result<Vec<jarkup_rs::Component>, crate::error::Error> {
+        let children = self.fetch_children(block_id).await?;
+
+        let context = self.create_context(
+            &crate::cancel::CancellationToken::new(),
+            std::time::Instant::now(),
+        );
+
+        self.convert_children(&context, &children)
+            .await
+            .map_err(|error| error.in_block(block_id))
+    }
+
+    #[async_recursion::async_recursion]
+    async fn convert_children(
+        &self,
+        context: &crate::context::ConversionContext,
+        trees: &[crate::tree::BlockTree],
+    ) -> Result<Vec<jarkup_rs::Component>, crate::error::Error> {
+        let child_results = try_join_all(trees.iter().map(|tree| async move {
+            if let Some(reason) = tree.truncated {
+                context.insert_truncation(&tree.block.id, reason);
+                return Ok(vec![
+                    self.create_truncation_placeholder(&tree.block.id, reason),
+                ]);
+            }
+
+            if let Some(subtree) = context.cached_subtree(&tree.block.id) {
+                context.restore_cached_subtree(&subtree);
+                return Ok(subtree.components.clone());
+            }
+
+            match &tree.children_error {
+                Some(message) => {
+                    context.insert_failure(&tree.block.id, message);
+                    Ok(self.create_failure_placeholder(&tree.block.id, message))
                 }
-                notionrs_types::object::block::Block::ChildDatabase { child_database: _ } => {
-                    if self.enable_unsupported_block {
-                        components.push(self.create_unsupported_component("ChildDatabase"));
-                    } else {
-                        continue;
+                None => {
+                    let children = self
+                        .convert_children(context, &tree.children)
+                        .await
+                        .map_err(|error| error.in_block(&tree.block.id));
+
+                    if let Ok(children) = &children {
+                        self.put_cached_subtree(context, tree, children).await;
                     }
+
+                    children
                 }
-                notionrs_types::object::block::Block::ChildPage { child_page: _ } => {
-                    if self.enable_unsupported_block {
-                        components.push(self.create_unsupported_component("ChildPage"));
-                    } else {
-                        continue;
+            }
+        }))
+        .await?;
+
+        for (block_id, list_start) in
+            crate::list::find_list_starts(trees.iter().map(|tree| &tree.block))
+        {
+            context.insert_list_start(block_id, list_start);
+        }
+
+        let futures: Vec<_> = trees
+            .iter()
+            .zip(child_results)
+            .map(|(tree, children)| async move {
+                match self
+                    .convert_block_response(context, &tree.block, children)
+                    .await
+                {
+                    Err(error)
+                        if !error.is_stopped()
+                            && self.options.on_error != crate::options::ErrorPolicy::Fail =>
+                    {
+                        let message = error.to_string();
+                        context.insert_failure(&tree.block.id, &message);
+                        Ok(self.create_failure_placeholder(&tree.block.id, &message))
                     }
+                    result => result.map_err(|error| error.in_block(&tree.block.id)),
                 }
-                notionrs_types::object::block::Block::Code { code } => {
-                    let component: jarkup_rs::Component = match code.language {
-                        Language::Mermaid => jarkup_rs::Mermaid {
-                            id: Some(block.id),
-                            props: jarkup_rs::MermaidProps {
-                                code: code
-                                    .rich_text
-                                    .clone()
-                                    .into_iter()
-                                    .map(|r| r.to_string())
-                                    .collect::<Vec<String>>()
-                                    .join(""),
-                            },
-                            slots: None,
-                        }
-                        .into(),
-                        _ => jarkup_rs::CodeBlock {
-                            id: Some(block.id),
-                            props: jarkup_rs::CodeBlockProps {
-                                code: code
-                                    .rich_text
-                                    .clone()
-                                    .into_iter()
-                                    .map(|r| r.to_string())
-                                    .collect::<Vec<String>>()
-                                    .join(""),
-                                language: code.language.to_string(),
-                            },
-                            slots: if code.caption.len() > 0 {
-                                Some(jarkup_rs::CodeBlockSlots {
-                                    default: self.convert_rich_text(code.caption).await?,
-                                })
-                            } else {
-                                None
-                            },
-                        }
-                        .into(),
-                    };
+            })
+            .collect();
 
-                    components.push(component);
-                }
-                notionrs_types::object::block::Block::ColumnList { .. } => {
-                    let columns = children_cache.remove(&block.id).unwrap_or_default();
+        // Convert siblings concurrently; `buffered` keeps the original order.
+        let converted: Vec<Vec<jarkup_rs::Component>> = futures::stream::iter(futures)
+            .buffered(self.options.network.concurrency_limit.max(1))
+            .try_collect()
+            .await?;
 
-                    let component = jarkup_rs::ColumnList {
-                        id: Some(block.id),
-                        props: None,
-                        slots: jarkup_rs::ColumnListSlots { default: columns },
-                    };
+        let mut components = Self::merge_list_components(context, converted.into_iter().flatten());
 
-                    components.push(component.into());
-                }
-                notionrs_types::object::block::Block::Column { column } => {
-                    let column_children = children_cache.remove(&block.id).unwrap_or_default();
-
-                    let component = jarkup_rs::Column {
-                        id: Some(block.id),
-                        props: Some(jarkup_rs::ColumnProps {
-                            width_ratio: Some(column.width_ratio as f32),
-                        }),
-                        slots: jarkup_rs::ColumnSlots {
-                            default: column_children,
-                        },
-                    };
+        crate::table::attach_captions(&mut components, &self.options.table_caption);
 
-                    components.push(component.into());
-                }
-                notionrs_types::object::block::Block::Divider { divider: _ } => {
-                    let component = jarkup_rs::Divider {
-                        id: Some(block.id),
+        Ok(components)
+    }
+
+    /// Converts a single block whose children have already been converted.
+    ///
+    /// List items are returned as single-item lists; adjacent lists are merged
+    /// afterwards by `merge_list_components`.
+    async fn convert_block_response(
+        &self,
+        context: &crate::context::ConversionContext,
+        block: &BlockResponse,
+        children: Vec<jarkup_rs::Component>,
+    ) -> Result<Vec<jarkup_rs::Component>, crate::error::Error> {
+        let mut components: Vec<jarkup_rs::Component> = Vec::new();
+
+        let block_id = block.id.clone();
+
+        match block.block.clone() {
+            notionrs_types::object::block::Block::Audio { audio: _ } => {
+                components.extend(self.convert_unsupported_block(context, &block_id, "Audio"));
+            }
+            notionrs_types::object::block::Block::Bookmark { bookmark } => {
+                let metadata = if self.options.network.fetch_bookmark_metadata {
+                    match context
+                        .outbound_request(self.fetch_bookmark_metadata(&bookmark.url))
+                        .await
+                    {
+                        Ok(metadata) => {
+                            context.insert_bookmark(block_id.clone(), metadata.clone());
+                            metadata
+                        }
+                        Err(error) if error.is_stopped() => {
+                            crate::bookmark::BookmarkMetadata::default()
+                        }
+                        Err(error) => {
+                            context.insert_metadata_failure(
+                                &block_id,
+                                &bookmark.url,
+                                crate::report::MetadataKind::Bookmark,
+                                &error.to_string(),
+                            );
+                            crate::bookmark::BookmarkMetadata::default()
+                        }
+                    }
+                } else {
+                    crate::bookmark::BookmarkMetadata::default()
+                };
+
+                let component = jarkup_rs::Bookmark {
+                    id: Some(block_id.clone()),
+                    props: jarkup_rs::BookmarkProps {
+                        url: bookmark.url,
+                        title: metadata.title,
+                        description: metadata.description,
+                        image: metadata.image,
+                    },
+                    slots: None,
+                };
+
+                components.push(component.into());
+
+                // `jarkup_rs::Bookmark` has no caption slot, so the caption follows it as a paragraph.
+                if !bookmark.caption.is_empty() {
+                    let caption = jarkup_rs::Paragraph {
+                        id: None,
                         props: None,
-                        slots: None,
+                        slots: jarkup_rs::ParagraphSlots {
+                            default: self
+                                .convert_block_rich_text(context, &block_id, bookmark.caption)
+                                .await?,
+                        },
                     };
 
-                    components.push(component.into());
+                    components.push(caption.into());
                 }
-                notionrs_types::object::block::Block::Embed { embed: _ } => {
-                    if self.enable_unsupported_block {
-                        components.push(self.create_unsupported_component("Embed"));
-                    } else {
-                        continue;
+            }
+            notionrs_types::object::block::Block::Breadcrumb { breadcrumb: _ } => {
+                components.extend(self.convert_unsupported_block(context, &block_id, "Breadcrumb"));
+            }
+            notionrs_types::object::block::Block::BulletedListItem { bulleted_list_item } => {
+                let inline_components: Vec<jarkup_rs::Component> = self
+                    .convert_colored_rich_text(
+                        context,
+                        &block_id,
+                        bulleted_list_item.rich_text,
+                        bulleted_list_item.color,
+                    )
+                    .await?
+                    .into_iter()
+                    .map(jarkup_rs::Component::InlineComponent)
+                    .collect();
+
+                let merged_components = inline_components
+                    .into_iter()
+                    .chain(children)
+                    .collect::<Vec<jarkup_rs::Component>>();
+
+                let list_item_component = jarkup_rs::ListItem {
+                    id: Some(block_id),
+                    props: None,
+                    slots: jarkup_rs::ListItemSlots {
+                        default: merged_components,
+                    },
+                };
+
+                let component = jarkup_rs::List {
+                    id: None,
+                    props: Some(jarkup_rs::ListProps {
+                        list_style: Some(jarkup_rs::ListStyle::Unordered),
+                    }),
+                    slots: jarkup_rs::ListSlots {
+                        default: vec![list_item_component.into()],
+                    },
+                };
+
+                components.push(component.into());
+            }
+            notionrs_types::object::block::Block::Callout { callout } => {
+                let icon_key = callout.icon.as_ref().and_then(|icon| match icon {
+                    notionrs_types::object::icon::Icon::Emoji(emoji) => Some(emoji.emoji.clone()),
+                    notionrs_types::object::icon::Icon::CustomEmoji(custom_emoji) => {
+                        Some(custom_emoji.custom_emoji.name.clone())
                     }
+                    notionrs_types::object::icon::Icon::File(_) => None,
+                });
+
+                let callout_type = self
+                    .options
+                    .callout
+                    .callout_type(callout.color, icon_key.as_deref());
+
+                let mut inline_components = self
+                    .convert_block_rich_text(context, &block_id, callout.rich_text)
+                    .await?;
+
+                if self.options.callout.emit_icon
+                    && let Some(icon) = callout.icon
+                    && let Some(icon_component) =
+                        self.convert_icon(context, &block_id, icon).await?
+                {
+                    inline_components.insert(0, icon_component);
                 }
-                notionrs_types::object::block::Block::Equation { equation } => {
-                    let component = jarkup_rs::Katex {
-                        id: Some(block.id),
-                        props: jarkup_rs::KatexProps {
-                            expression: equation.expression,
-                        },
-                        slots: None,
+
+                let maybe_paragraph_component: Option<jarkup_rs::Component> =
+                    if !inline_components.is_empty() {
+                        Some(
+                            jarkup_rs::Paragraph {
+                                id: Some(block_id.clone()),
+                                props: self.map_paragraph_props(callout.color),
+                                slots: jarkup_rs::ParagraphSlots {
+                                    default: inline_components,
+                                },
+                            }
+                            .into(),
+                        )
+                    } else {
+                        None
                     };
 
-                    components.push(component.into());
-                }
-                notionrs_types::object::block::Block::File { file } => {
-                    let component = jarkup_rs::File {
-                        id: Some(block.id),
-                        props: jarkup_rs::FileProps {
-                            src: file.get_url(),
-                            name: match file {
-                                notionrs_types::object::file::File::External(external_file) => {
-                                    external_file.name
-                                }
-                                notionrs_types::object::file::File::NotionHosted(
-                                    notion_hosted_file,
-                                ) => notion_hosted_file.name,
-                                _ => Some(String::from("untitled")),
-                            },
+                let merged_children_components = maybe_paragraph_component
+                    .into_iter()
+                    .chain(children)
+                    .collect::<Vec<jarkup_rs::Component>>();
+
+                let component = jarkup_rs::Callout {
+                    id: Some(block_id),
+                    props: Some(jarkup_rs::CalloutProps {
+                        r#type: Some(callout_type),
+                    }),
+                    slots: jarkup_rs::CalloutSlots {
+                        default: merged_children_components,
+                    },
+                };
+
+                components.push(component.into());
+            }
+            notionrs_types::object::block::Block::ChildDatabase { child_database: _ } => {
+                components.extend(self.convert_unsupported_block(
+                    context,
+                    &block_id,
+                    "ChildDatabase",
+                ));
+            }
+            notionrs_types::object::block::Block::ChildPage { child_page: _ } => {
+                components.extend(self.convert_unsupported_block(context, &block_id, "ChildPage"));
+            }
+            notionrs_types::object::block::Block::Code { code } => {
+                let component: jarkup_rs::Component = match code.language {
+                    Language::Mermaid => jarkup_rs::Mermaid {
+                        id: Some(block_id),
+                        props: jarkup_rs::MermaidProps {
+                            code: code
+                                .rich_text
+                                .clone()
+                                .into_iter()
+                                .map(|r| r.to_string())
+                                .collect::<Vec<String>>()
+                                .join(""),
                         },
                         slots: None,
-                    };
+                    }
+                    .into(),
+                    _ => jarkup_rs::CodeBlock {
+                        id: Some(block_id.clone()),
+                        props: jarkup_rs::CodeBlockProps {
+                            code: code
+                                .rich_text
+                                .clone()
+                                .into_iter()
+                                .map(|r| r.to_string())
+                                .collect::<Vec<String>>()
+                                .join(""),
+                            language: code.language.to_string(),
+                        },
+                        slots: if !code.caption.is_empty() {
+                            Some(jarkup_rs::CodeBlockSlots {
+                                default: self
+                                    .convert_block_rich_text(context, &block_id, code.caption)
+                                    .await?,
+                            })
+                        } else {
+                            None
+                        },
+                    }
+                    .into(),
+                };
 
-                    components.push(component.into());
-                }
-                notionrs_types::object::block::Block::Heading1 { heading_1 } => {
-                    let children = children_cache.remove(&block.id).unwrap_or_default();
-                    let component = self
-                        .convert_heading_block(
-                            heading_1,
-                            &block.id,
-                            jarkup_rs::HeadingLevel::H1,
-                            children,
-                        )
-                        .await?;
+                components.push(component);
+            }
+            notionrs_types::object::block::Block::ColumnList { .. } => {
+                let component = jarkup_rs::ColumnList {
+                    id: Some(block_id),
+                    props: None,
+                    slots: jarkup_rs::ColumnListSlots { default: children },
+                };
+
+                components.push(component.into());
+            }
+            notionrs_types::object::block::Block::Column { column } => {
+                let component = jarkup_rs::Column {
+                    id: Some(block_id),
+                    props: Some(jarkup_rs::ColumnProps {
+                        width_ratio: Some(column.width_ratio as f32),
+                    }),
+                    slots: jarkup_rs::ColumnSlots { default: children },
+                };
+
+                components.push(component.into());
+            }
+            notionrs_types::object::block::Block::Divider { divider: _ } => {
+                let component = jarkup_rs::Divider {
+                    id: Some(block_id),
+                    props: None,
+                    slots: None,
+                };
+
+                components.push(component.into());
+            }
+            notionrs_types::object::block::Block::Embed { embed: _ } => {
+                components.extend(self.convert_unsupported_block(context, &block_id, "Embed"));
+            }
+            notionrs_types::object::block::Block::Equation { equation } => {
+                let component = jarkup_rs::Katex {
+                    id: Some(block_id),
+                    props: jarkup_rs::KatexProps {
+                        expression: equation.expression,
+                    },
+                    slots: None,
+                };
+
+                components.push(component.into());
+            }
+            notionrs_types::object::block::Block::File { file } => {
+                let (src, name) = match file {
+                    notionrs_types::object::file::File::External(external_file) => {
+                        (external_file.external.url, external_file.name)
+                    }
+                    notionrs_types::object::file::File::NotionHosted(notion_hosted_file) => (
+                        self.mirror_asset(context, &block_id, &notion_hosted_file.file.url)
+                            .await?,
+                        notion_hosted_file.name,
+                    ),
+                    // Files uploaded through the API have no URL in the block.
+                    _ => {
+                        components
+                            .extend(self.convert_unsupported_block(context, &block_id, "File"));
+                        return Ok(components);
+                    }
+                };
 
-                    if let Some(c) = component {
-                        components.push(c);
-                    } else {
-                        continue;
-                    };
-                }
-                notionrs_types::object::block::Block::Heading2 { heading_2 } => {
-                    let children = children_cache.remove(&block.id).unwrap_or_default();
-                    let component = self
-                        .convert_heading_block(
-                            heading_2,
-                            &block.id,
-                            jarkup_rs::HeadingLevel::H2,
-                            children,
-                        )
-                        .await?;
+                let component = jarkup_rs::File {
+                    id: Some(block_id),
+                    props: jarkup_rs::FileProps { src, name },
+                    slots: None,
+                };
 
-                    if let Some(c) = component {
-                        components.push(c);
-                    } else {
-                        continue;
-                    };
-                }
-                notionrs_types::object::block::Block::Heading3 { heading_3 } => {
-                    let children = children_cache.remove(&block.id).unwrap_or_default();
-                    let component = self
-                        .convert_heading_block(
-                            heading_3,
-                            &block.id,
-                            jarkup_rs::HeadingLevel::H3,
-                            children,
-                        )
-                        .await?;
+                components.push(component.into());
+            }
+            notionrs_types::object::block::Block::Heading1 { heading_1 } => {
+                let heading_components = self
+                    .convert_heading_block(
+                        context,
+                        heading_1,
+                        &block_id,
+                        jarkup_rs::HeadingLevel::H1,
+                        children,
+                    )
+                    .await?;
+
+                components.extend(heading_components);
+            }
+            notionrs_types::object::block::Block::Heading2 { heading_2 } => {
+                let heading_components = self
+                    .convert_heading_block(
+                        context,
+                        heading_2,
+                        &block_id,
+                        jarkup_rs::HeadingLevel::H2,
+                        children,
+                    )
+                    .await?;
+
+                components.extend(heading_components);
+            }
+            notionrs_types::object::block::Block::Heading3 { heading_3 } => {
+                let heading_components = self
+                    .convert_heading_block(
+                        context,
+                        heading_3,
+                        &block_id,
+                        jarkup_rs::HeadingLevel::H3,
+                        children,
+                    )
+                    .await?;
+
+                components.extend(heading_components);
+            }
+            notionrs_types::object::block::Block::Image { image } => {
+                let (original_src, caption, is_notion_hosted) = match image {
+                    notionrs_types::object::file::File::External(external_file) => {
+                        (external_file.external.url, external_file.caption, false)
+                    }
+                    notionrs_types::object::file::File::NotionHosted(notion_hosted_file) => (
+                        notion_hosted_file.file.url,
+                        notion_hosted_file.caption,
+                        true,
+                    ),
+                    // Files uploaded through the API have no URL in the block.
+                    _ => {
+                        components
+                            .extend(self.convert_unsupported_block(context, &block_id, "Image"));
+                        return Ok(components);
+                    }
+                };
 
-                    if let Some(c) = component {
-                        components.push(c);
-                    } else {
-                        continue;
+                let (src, downloaded) = if is_notion_hosted {
+                    self.mirror_asset_with_bytes(context, &block_id, &original_src)
+                        .await?
+                } else {
+                    (original_src.clone(), None)
+                };
+
+                if self.options.image_analysis.enabled {
+                    let image_info = match downloaded {
+                        // The file copied to the asset store is analysed as is.
+                        Some(bytes) => self.analyze_image_bytes(bytes).await,
+                        None => context
+                            .outbound_request(async { Ok(self.analyze_image(&original_src).await) })
+                            .await
+                            .ok()
+                            .flatten(),
                     };
+
+                    if let Some(image_info) = image_info {
+                        context.insert_image(block_id.clone(), image_info);
+                    }
                 }
-                notionrs_types::object::block::Block::Image { image } => {
-                    let maybe_caption = match image.clone() {
-                        notionrs_types::object::file::File::External(external_file) => {
-                            external_file
-                                .caption
-                                .map(|c| c.into_iter().map(|c| c.to_string()).collect::<String>())
-                        }
-                        notionrs_types::object::file::File::NotionHosted(notion_hosted_file) => {
-                            notion_hosted_file
-                                .caption
-                                .map(|c| c.into_iter().map(|c| c.to_string()).collect::<String>())
-                        }
-                        _ => Some(String::from("untitled")),
-                    };
 
-                    let component = jarkup_rs::Image {
-                        id: Some(block.id),
-                        props: jarkup_rs::ImageProps {
-                            src: image.get_url(),
-                            alt: maybe_caption,
-                        },
-                        slots: None,
+                let caption = self
+                    .convert_block_rich_text(context, &block_id, caption.unwrap_or_default())
+                    .await?;
+
+                let caption_text = crate::visit::plain_text(&caption);
+                let caption_text = caption_text.trim();
+
+                // An empty `alt` marks the image as decorative.
+                let alt = self
+                    .image_alt_text
+                    .as_ref()
+                    .and_then(|image_alt_text| image_alt_text.alt_text(&block_id, &src))
+                    .unwrap_or_else(|| caption_text.to_owned());
+
+                let component = jarkup_rs::Image {
+                    id: Some(block_id),
+                    props: jarkup_rs::ImageProps {
+                        src,
+                        alt: Some(alt),
+                    },
+                    slots: None,
+                };
+
+                components.push(component.into());
+
+                // `jarkup_rs::Image` has no caption slot, so the caption follows it as a paragraph.
+                if !caption.is_empty() {
+                    let caption = jarkup_rs::Paragraph {
+                        id: None,
+                        props: None,
+                        slots: jarkup_rs::ParagraphSlots { default: caption },
                     };
 
-                    components.push(component.into());
+                    components.push(caption.into());
                 }
-                notionrs_types::object::block::Block::LinkPreview { link_preview: _ } => {
-                    if self.enable_unsupported_block {
-                        components.push(self.create_unsupported_component("LinkPreview"));
+            }
+            notionrs_types::object::block::Block::LinkPreview { link_preview: _ } => {
+                components.extend(self.convert_unsupported_block(
+                    context,
+                    &block_id,
+                    "LinkPreview",
+                ));
+            }
+            notionrs_types::object::block::Block::NumberedListItem { numbered_list_item } => {
+                let inline_components: Vec<jarkup_rs::Component> = self
+                    .convert_colored_rich_text(
+                        context,
+                        &block_id,
+                        numbered_list_item.rich_text,
+                        numbered_list_item.color,
+                    )
+                    .await?
+                    .into_iter()
+                    .map(jarkup_rs::Component::InlineComponent)
+                    .collect();
+
+                let merged_components = inline_components
+                    .into_iter()
+                    .chain(children)
+                    .collect::<Vec<jarkup_rs::Component>>();
+
+                let list_item_component = jarkup_rs::ListItem {
+                    id: Some(block_id),
+                    props: None,
+                    slots: jarkup_rs::ListItemSlots {
+                        default: merged_components,
+                    },
+                };
+
+                let component = jarkup_rs::List {
+                    id: None,
+                    props: Some(jarkup_rs::ListProps {
+                        list_style: Some(jarkup_rs::ListStyle::Ordered),
+                    }),
+                    slots: jarkup_rs::ListSlots {
+                        default: vec![list_item_component.into()],
+                    },
+                };
+
+                components.push(component.into());
+            }
+            notionrs_types::object::block::Block::Paragraph { paragraph } => {
+                let component = jarkup_rs::Paragraph {
+                    id: Some(block_id.clone()),
+                    props: Some(jarkup_rs::ParagraphProps {
+                        color: self.map_color(paragraph.color),
+                        background_color: self.map_background_color(paragraph.color),
+                    }),
+                    slots: jarkup_rs::ParagraphSlots {
+                        default: self
+                            .convert_block_rich_text(context, &block_id, paragraph.rich_text)
+                            .await?,
+                    },
+                };
+
+                components.push(component.into());
+            }
+            notionrs_types::object::block::Block::Pdf { pdf: _ } => {
+                components.extend(self.convert_unsupported_block(context, &block_id, "Pdf"));
+            }
+            notionrs_types::object::block::Block::Quote { quote } => {
+                let maybe_paragraph_component: Option<jarkup_rs::Component> =
+                    if !quote.rich_text.is_empty() {
+                        let paragraph = jarkup_rs::Paragraph {
+                            id: Some(block_id.clone()),
+                            props: self.map_paragraph_props(quote.color),
+                            slots: jarkup_rs::ParagraphSlots {
+                                default: self
+                                    .convert_block_rich_text(context, &block_id, quote.rich_text)
+                                    .await?,
+                            },
+                        };
+                        Some(paragraph.into())
                     } else {
-                        continue;
-                    }
-                }
-                notionrs_types::object::block::Block::NumberedListItem { numbered_list_item } => {
-                    let inline_components: Vec<jarkup_rs::Component> = self
-                        .convert_rich_text(numbered_list_item.rich_text)
-                        .await?
-                        .into_iter()
-                        .map(jarkup_rs::Component::InlineComponent)
-                        .collect();
-
-                    let children_components = children_cache.remove(&block.id).unwrap_or_default();
-
-                    let merged_components = inline_components
-                        .into_iter()
-                        .chain(children_components)
-                        .collect::<Vec<jarkup_rs::Component>>();
-
-                    let list_item_component = jarkup_rs::ListItem {
-                        id: Some(block.id),
-                        props: None,
-                        slots: jarkup_rs::ListItemSlots {
-                            default: merged_components,
-                        },
+                        None
                     };
 
-                    let maybe_prev_component = components.last_mut().and_then(|c| match c {
-                        jarkup_rs::Component::InlineComponent(_) => None,
-                        jarkup_rs::Component::BlockComponent(block_component) => {
-                            match block_component {
-                                jarkup_rs::BlockComponent::List(list) => Some(list),
-                                _ => None,
+                // Without inline content the quote color has no paragraph of
+                // its own, so it goes onto the child paragraphs where unset.
+                let children = if maybe_paragraph_component.is_none()
+                    && let Some(quote_props) = self.map_paragraph_props(quote.color)
+                {
+                    children
+                        .into_iter()
+                        .map(|child| match child {
+                            jarkup_rs::Component::BlockComponent(
+                                jarkup_rs::BlockComponent::Paragraph(mut paragraph),
+                            ) => {
+                                let props = paragraph.props.get_or_insert_default();
+                                if props.color.is_none() && props.background_color.is_none() {
+                                    props.color = quote_props.color.clone();
+                                    props.background_color = quote_props.background_color.clone();
+                                }
+                                paragraph.into()
                             }
+                            child => child,
+                        })
+                        .collect()
+                } else {
+                    children
+                };
+
+                let merged_components = maybe_paragraph_component
+                    .into_iter()
+                    .chain(children)
+                    .collect::<Vec<jarkup_rs::Component>>();
+
+                let component = jarkup_rs::BlockQuote {
+                    id: Some(block_id),
+                    props: None,
+                    slots: jarkup_rs::BlockQuoteSlots {
+                        default: merged_components,
+                    },
+                };
+
+                components.push(component.into());
+            }
+            notionrs_types::object::block::Block::SyncedBlock { synced_block: _ } => {
+                components.extend(self.convert_unsupported_block(
+                    context,
+                    &block_id,
+                    "SyncedBlock",
+                ));
+            }
+            notionrs_types::object::block::Block::TableOfContents {
+                table_of_contents: _,
+            } => {}
+            notionrs_types::object::block::Block::Table { table } => {
+                let mut all_children_rows = children;
+
+                let maybe_header_row = if table.has_column_header && !all_children_rows.is_empty() {
+                    match all_children_rows.remove(0) {
+                        jarkup_rs::Component::BlockComponent(
+                            jarkup_rs::BlockComponent::TableRow(mut table_row),
+                        ) => {
+                            crate::table::mark_header_cells(
+                                &mut table_row,
+                                true,
+                                table.has_row_header,
+                            );
+                            Some(vec![table_row.into()])
                         }
-                    });
-
-                    match maybe_prev_component {
-                        Some(prev_component) => {
-                            let is_ordered = prev_component
-                                .props
-                                .clone()
-                                .map(|p| {
-                                    matches!(p.list_style, Some(jarkup_rs::ListStyle::Ordered))
-                                })
-                                .unwrap_or(true);
-
-                            if is_ordered {
-                                prev_component
-                                    .slots
-                                    .default
-                                    .push(list_item_component.into());
-
-                                continue;
-                            }
+                        _ => None,
+                    }
+                } else {
+                    None
+                };
+
+                let body_rows = all_children_rows
+                    .into_iter()
+                    .filter_map(|row| match row {
+                        jarkup_rs::Component::BlockComponent(
+                            jarkup_rs::BlockComponent::TableRow(mut table_row),
+                        ) => {
+                            crate::table::mark_header_cells(
+                                &mut table_row,
+                                false,
+                                table.has_row_header,
+                            );
+                            Some(table_row.into())
                         }
+                        _ => None,
+                    })
+                    .collect::<Vec<jarkup_rs::Component>>();
+
+                let component = jarkup_rs::Table {
+                    id: Some(block_id),
+                    props: Some(jarkup_rs::TableProps {
+                        has_column_header: Some(table.has_column_header),
+                        has_row_header: Some(table.has_row_header),
+                        caption: None,
+                    }),
+                    slots: jarkup_rs::TableSlots {
+                        header: maybe_header_row,
+                        body: body_rows,
+                    },
+                };
+
+                components.push(component.into());
+            }
+            notionrs_types::object::block::Block::TableRow { table_row } => {
+                let mut cell_components: Vec<jarkup_rs::Component> = Vec::new();
 
-                        None => {}
-                    };
+                for cell in table_row.cells {
+                    let children_inline_componense = self
+                        .convert_block_rich_text(context, &block_id, cell)
+                        .await?;
 
-                    let component = jarkup_rs::List {
+                    let component = jarkup_rs::TableCell {
                         id: None,
-                        props: Some(jarkup_rs::ListProps {
-                            list_style: Some(jarkup_rs::ListStyle::Ordered),
-                        }),
-                        slots: jarkup_rs::ListSlots {
-                            default: vec![list_item_component.into()],
-                        },
-                    };
-
-                    components.push(component.into());
-                }
-                notionrs_types::object::block::Block::Paragraph { paragraph } => {
-                    let component = jarkup_rs::Paragraph {
-                        id: Some(block.id),
-                        props: Some(jarkup_rs::ParagraphProps {
-                            color: Self::map_color(paragraph.color),
-                            background_color: Self::map_background_color(paragraph.color),
-                        }),
-                        slots: jarkup_rs::ParagraphSlots {
-                            default: self.convert_rich_text(paragraph.rich_text).await?,
+                        props: None,
+                        slots: jarkup_rs::TableCellSlots {
+                            default: children_inline_componense,
                         },
                     };
 
-                    components.push(component.into());
+                    cell_components.push(component.into());
                 }
-                notionrs_types::object::block::Block::Pdf { pdf: _ } => {}
-                notionrs_types::object::block::Block::Quote { quote } => {
-                    let maybe_paragraph_component: Option<jarkup_rs::Component> =
-                        if quote.rich_text.len() > 0 {
-                            let paragraph = jarkup_rs::Paragraph {
-                                id: Some(block.id.clone()),
-                                props: None,
-                                slots: jarkup_rs::ParagraphSlots {
-                                    default: self.convert_rich_text(quote.rich_text).await?,
-                                },
-                            };
-                            Some(paragraph.into())
-                        } else {
-                            None
-                        };
 
-                    let maybe_children_components =
-                        children_cache.remove(&block.id).unwrap_or_default();
+                let row_component = jarkup_rs::TableRow {
+                    id: Some(block_id),
+                    props: None,
+                    slots: jarkup_rs::TableRowSlots {
+                        default: cell_components,
+                    },
+                };
 
-                    let merged_components = maybe_paragraph_component
-                        .into_iter()
-                        .chain(maybe_children_components)
-                        .collect::<Vec<jarkup_rs::Component>>();
+                components.push(row_component.into());
+            }
+            notionrs_types::object::block::Block::Template { template: _ } => {
+                components.extend(self.convert_unsupported_block(context, &block_id, "Template"));
+            }
+            notionrs_types::object::block::Block::ToDo { to_do: _ } => {
+                components.extend(self.convert_unsupported_block(context, &block_id, "ToDo"));
+            }
+            notionrs_types::object::block::Block::Toggle { toggle } => {
+                let summary_components = self
+                    .convert_colored_rich_text(context, &block_id, toggle.rich_text, toggle.color)
+                    .await?;
+
+                let component = jarkup_rs::Toggle {
+                    id: Some(block_id),
+                    props: None,
+                    slots: jarkup_rs::ToggleSlots {
+                        default: children,
+                        summary: summary_components,
+                    },
+                };
+
+                components.push(component.into());
+            }
+            notionrs_types::object::block::Block::Video { video: _ } => {
+                components.extend(self.convert_unsupported_block(context, &block_id, "Video"));
+            }
+            _ => {
+                components.extend(self.convert_unsupported_block(
+                    context,
+                    &block_id,
+                    "Unsupported",
+                ));
+            }
+        }
 
-                    let component = jarkup_rs::BlockQuote {
-                        id: Some(block.id),
-                        props: None,
-                        slots: jarkup_rs::BlockQuoteSlots {
-                            default: merged_components,
-                        },
-                    };
+        Ok(components)
+    }
 
-                    components.push(component.into());
+    /// Merges adjacent lists of the same style into a single list, unless the
+    /// later one starts a new Notion list (`list_start_index` / `list_format`).
+    fn merge_list_components(
+        context: &crate::context::ConversionContext,
+        components: impl IntoIterator<Item = jarkup_rs::Component>,
+    ) -> Vec<jarkup_rs::Component> {
+        let mut merged: Vec<jarkup_rs::Component> = Vec::new();
+
+        for component in components {
+            match (merged.last_mut(), component) {
+                (
+                    Some(jarkup_rs::Component::BlockComponent(jarkup_rs::BlockComponent::List(
+                        prev_list,
+                    ))),
+                    jarkup_rs::Component::BlockComponent(jarkup_rs::BlockComponent::List(list)),
+                ) if Self::is_same_list_style(&prev_list.props, &list.props)
+                    && !Self::starts_new_list(context, &list) =>
+                {
+                    prev_list.slots.default.extend(list.slots.default);
                 }
-                notionrs_types::object::block::Block::SyncedBlock { synced_block: _ } => {
-                    if self.enable_unsupported_block {
-                        components.push(self.create_unsupported_component("SyncedBlock"));
-                    } else {
-                        continue;
-                    }
-                }
-                notionrs_types::object::block::Block::TableOfContents {
-                    table_of_contents: _,
-                } => continue,
-                notionrs_types::object::block::Block::Table { table } => {
-                    let mut all_children_rows =
-                        children_cache.remove(&block.id).unwrap_or_default();
-
-                    let maybe_header_row =
-                        if table.has_column_header && all_children_rows.len() > 0 {
-                            let first = all_children_rows.remove(0);
-                            let maybe_row_components = if let jarkup_rs::Component::BlockComponent(
-                                block_component,
-                            ) = first
-                            {
-                                if let jarkup_rs::BlockComponent::TableRow(table_row) =
-                                    block_component
-                                {
-                                    Some(table_row.to_owned().into())
-                                } else {
-                                    None
-                                }
-                            } else {
-                                None
-                            };
-
-                            Some(maybe_row_components)
-                        } else {
-                            None
-                        }
-                        .flatten()
-                        .map(|table_row| vec![table_row]);
+                (_, component) => merged.push(component),
+            }
+        }
 
-                    let body_rows = all_children_rows
-                        .into_iter()
-                        .filter_map(|row| {
-                            let maybe_row_component = if let jarkup_rs::Component::BlockComponent(
-                                block_component,
-                            ) = row
-                            {
-                                if let jarkup_rs::BlockComponent::TableRow(table_row) =
-                                    block_component
-                                {
-                                    Some(table_row.to_owned().into())
-                                } else {
-                                    None
-                                }
-                            } else {
-                                None
-                            };
+        merged
+    }
 
-                            maybe_row_component
-                        })
-                        .collect::<Vec<jarkup_rs::Component>>();
-
-                    let component = jarkup_rs::Table {
-                        id: Some(block.id),
-                        props: Some(jarkup_rs::TableProps {
-                            has_column_header: Some(table.has_column_header),
-                            has_row_header: Some(table.has_row_header),
-                            caption: None,
-                        }),
-                        slots: jarkup_rs::TableSlots {
-                            header: maybe_header_row,
-                            body: body_rows,
-                        },
-                    };
+    fn starts_new_list(
+        context: &crate::context::ConversionContext,
+        list: &jarkup_rs::List,
+    ) -> bool {
+        match list.slots.default.first() {
+            Some(jarkup_rs::Component::BlockComponent(jarkup_rs::BlockComponent::ListItem(
+                list_item,
+            ))) => list_item
+                .id
+                .as_deref()
+                .is_some_and(|id| context.is_list_start(id)),
+            _ => false,
+        }
+    }
 
-                    components.push(component.into());
-                }
-                notionrs_types::object::block::Block::TableRow { table_row } => {
-                    let mut cell_components: Vec<jarkup_rs::Component> = Vec::new();
+    fn is_same_list_style(
+        a: &Option<jarkup_rs::ListProps>,
+        b: &Option<jarkup_rs::ListProps>,
+    ) -> bool {
+        let style = |props: &Option<jarkup_rs::ListProps>| {
+            props.as_ref().and_then(|p| p.list_style.clone())
+        };
 
-                    for cell in table_row.cells {
-                        let children_inline_componense = self.convert_rich_text(cell).await?;
+        matches!(
+            (style(a), style(b)),
+            (
+                Some(jarkup_rs::ListStyle::Unordered),
+                Some(jarkup_rs::ListStyle::Unordered)
+            ) | (
+                Some(jarkup_rs::ListStyle::Ordered),
+                Some(jarkup_rs::ListStyle::Ordered)
+            )
+        )
+    }
 
-                        let component = jarkup_rs::TableCell {
-                            id: None,
-                            props: None,
-                            slots: jarkup_rs::TableCellSlots {
-                                default: children_inline_componense,
-                            },
-                        };
+    pub async fn convert_rich_text(
+        &self,
+        rich_text_vec: Vec<RichText>,
+    ) -> Result<Vec<jarkup_rs::InlineComponent>, crate::error::Error> {
+        let context = self.create_context(
+            &crate::cancel::CancellationToken::new(),
+            std::time::Instant::now(),
+        );
 
-                        cell_components.push(component.into());
-                    }
+        self.convert_block_rich_text(&context, "", rich_text_vec)
+            .await
+    }
 
-                    let row_component = jarkup_rs::TableRow {
-                        id: Some(block.id),
-                        props: None,
-                        slots: jarkup_rs::TableRowSlots {
-                            default: cell_components,
-                        },
-                    };
+    /// Converts the rich text of `block_id`, recording what is dropped in the report.
+    async fn convert_block_rich_text(
+        &self,
+        context: &crate::context::ConversionContext,
+        block_id: &str,
+        rich_text_vec: Vec<RichText>,
+    ) -> Result<Vec<jarkup_rs::InlineComponent>, crate::error::Error> {
+        let components: Vec<Option<jarkup_rs::InlineComponent>> =
+            futures::stream::iter(rich_text_vec)
+                .map(|rich_text| self.convert_rich_text_item(context, block_id, rich_text))
+                .buffered(self.options.network.concurrency_limit.max(1))
+                .try_collect()
+                .await?;
+
+        Ok(components.into_iter().flatten().collect())
+    }
 
-                    components.push(row_component.into());
-                }
-                notionrs_types::object::block::Block::Template { template: _ } => {
-                    if self.enable_unsupported_block {
-                        components.push(self.create_unsupported_component("Template"));
-                    } else {
-                        continue;
-                    }
-                }
-                notionrs_types::object::block::Block::ToDo { to_do: _ } => {
-                    if self.enable_unsupported_block {
-                        components.push(self.create_unsupported_component("ToDo"));
-                    } else {
-                        continue;
-                    }
-                }
-                notionrs_types::object::block::Block::Toggle { toggle } => {
-                    let children_components = children_cache.remove(&block.id).unwrap_or_default();
+    /// Converts rich text belonging to a block whose jarkup component has no
+    /// color props; the block color is applied to texts without a color of their own.
+    async fn convert_colored_rich_text(
+        &self,
+        context: &crate::context::ConversionContext,
+        block_id: &str,
+        rich_text_vec: Vec<RichText>,
+        block_color: Color,
+    ) -> Result<Vec<jarkup_rs::InlineComponent>, crate::error::Error> {
+        let mut components = self
+            .convert_block_rich_text(context, block_id, rich_text_vec)
+            .await?;
 
-                    let summary_components = self.convert_rich_text(toggle.rich_text).await?;
+        let color = self.map_color(block_color);
+        let background_color = self.map_background_color(block_color);
 
-                    let component = jarkup_rs::Toggle {
-                        id: Some(block.id),
-                        props: None,
-                        slots: jarkup_rs::ToggleSlots {
-                            default: children_components,
-                            summary: summary_components,
-                        },
-                    };
+        if color.is_none() && background_color.is_none() {
+            return Ok(components);
+        }
 
-                    components.push(component.into());
-                }
-                notionrs_types::object::block::Block::Video { video: _ } => {
-                    if self.enable_unsupported_block {
-                        components.push(self.create_unsupported_component("Video"));
-                    } else {
-                        continue;
-                    }
+        for component in components.iter_mut() {
+            if let jarkup_rs::InlineComponent::Text(text) = component {
+                if text.props.color.is_none() {
+                    text.props.color = color.clone();
                 }
-                _ => {
-                    if self.enable_unsupported_block {
-                        components.push(self.create_unsupported_component("Unsupported"));
-                    } else {
-                        continue;
-                    }
+                if text.props.background_color.is_none() {
+                    text.props.background_color = background_color.clone();
                 }
             }
         }
@@ -754,199 +1525,490 @@ impl Client {
         Ok(components)
     }
 
-    pub async fn convert_rich_text(
+    /// Converts a single rich text. Returns `None` for rich texts that are skipped.
+    async fn convert_rich_text_item(
         &self,
-        rich_text_vec: Vec<RichText>,
-    ) -> Result<Vec<jarkup_rs::InlineComponent>, crate::error::Error> {
-        let mut components: Vec<jarkup_rs::InlineComponent> = Vec::new();
-
-        for rich_text in rich_text_vec {
-            let component: Result<jarkup_rs::InlineComponent, crate::error::Error> = match rich_text
-            {
-                RichText::Text {
-                    text,
-                    annotations,
-                    plain_text,
-                    href: _,
-                } => {
-                    let component = if self.is_kbd(&plain_text, annotations.code) {
-                        jarkup_rs::Text {
-                            props: jarkup_rs::TextProps {
-                                text: plain_text,
-                                kbd: Some(true),
-                                ..Default::default()
-                            },
+        context: &crate::context::ConversionContext,
+        block_id: &str,
+        rich_text: RichText,
+    ) -> Result<Option<jarkup_rs::InlineComponent>, crate::error::Error> {
+        let component: jarkup_rs::InlineComponent = match rich_text {
+            RichText::Text {
+                text,
+                annotations,
+                plain_text,
+                href: _,
+            } => {
+                let component = if self.is_kbd(&plain_text, annotations.code) {
+                    jarkup_rs::Text {
+                        props: jarkup_rs::TextProps {
+                            text: plain_text,
+                            kbd: Some(true),
                             ..Default::default()
-                        }
-                    } else {
-                        jarkup_rs::Text {
-                            id: None,
-                            props: jarkup_rs::TextProps {
-                                text: plain_text,
-                                color: Self::map_color(annotations.color),
-                                background_color: Self::map_background_color(annotations.color),
-                                bold: Some(annotations.bold),
-                                italic: Some(annotations.italic),
-                                underline: Some(annotations.underline),
-                                strikethrough: Some(annotations.strikethrough),
-                                katex: None,
-                                code: Some(annotations.code),
-                                kbd: None,
-                                ruby: None,
-                                favicon: if let Some(l) = &text.link {
-                                    self.fetch_favicon_by_url(&l.url).await
-                                } else {
-                                    None
-                                },
-                                href: text.link.map(|l| l.url),
+                        },
+                        ..Default::default()
+                    }
+                } else {
+                    jarkup_rs::Text {
+                        id: None,
+                        props: jarkup_rs::TextProps {
+                            text: plain_text,
+                            color: self.map_color(annotations.color),
+                            background_color: self.map_background_color(annotations.color),
+                            bold: Some(annotations.bold),
+                            italic: Some(annotations.italic),
+                            underline: Some(annotations.underline),
+                            strikethrough: Some(annotations.strikethrough),
+                            katex: None,
+                            code: Some(annotations.code),
+                            kbd: None,
+                            ruby: None,
+                            favicon: if let Some(l) = &text.link {
+                                self.fetch_favicon_by_url(context, block_id, &l.url).await
+                            } else {
+                                None
                             },
-                            slots: None,
-                        }
-                    };
+                            href: text.link.map(|l| l.url),
+                        },
+                        slots: None,
+                    }
+                };
 
-                    Ok(component.into())
+                component.into()
+            }
+            RichText::Mention {
+                mention,
+                annotations: _annotations,
+                plain_text,
+                href: _href,
+            } => match mention {
+                Mention::User { user: _ } => {
+                    context.insert_dropped_mention(block_id, "user", &plain_text);
+                    return Ok(None);
                 }
-                RichText::Mention {
-                    mention,
-                    annotations: _annotations,
-                    plain_text,
-                    href: _href,
-                } => {
-                    let component: Result<jarkup_rs::InlineComponent, crate::error::Error> =
-                        match mention {
-                            Mention::User { user: _ } => {
-                                continue;
-                            }
-                            Mention::Date { date: _ } => {
-                                continue;
-                            }
-                            Mention::LinkPreview { link_preview: _ } => {
-                                continue;
-                            }
-                            Mention::LinkMention { link_mention } => {
-                                let component = jarkup_rs::Text {
-                                    id: None,
-                                    props: jarkup_rs::TextProps {
-                                        text: plain_text,
-                                        favicon: self
-                                            .fetch_favicon_by_url(&link_mention.href)
-                                            .await,
-                                        href: Some(link_mention.href),
-                                        ..Default::default()
-                                    },
-                                    slots: None,
-                                };
-
-                                let inline_component: jarkup_rs::InlineComponent = component.into();
-
-                                Ok(inline_component)
-                            }
-                            Mention::TemplateMention {
-                                template_mention: _,
-                            } => {
-                                continue;
-                            }
-                            Mention::Page { page: _ } => {
-                                continue;
-                            }
-                            Mention::Database { database: _ } => {
-                                continue;
-                            }
-                            Mention::CustomEmoji { custom_emoji } => {
-                                let component = jarkup_rs::Icon {
-                                    id: Some(custom_emoji.id),
-                                    props: jarkup_rs::IconProps {
-                                        src: custom_emoji.url,
-                                        alt: Some(custom_emoji.name),
-                                    },
-                                    slots: None,
-                                };
-
-                                let inline_component: jarkup_rs::InlineComponent = component.into();
-
-                                Ok(inline_component)
-                            }
-                        };
-
-                    component
+                Mention::Date { date: _ } => {
+                    context.insert_dropped_mention(block_id, "date", &plain_text);
+                    return Ok(None);
                 }
-                RichText::Equation {
-                    equation,
-                    annotations: _annotations,
-                    plain_text: _plain_text,
-                    href: _href,
-                } => {
+                Mention::LinkPreview { link_preview: _ } => {
+                    context.insert_dropped_mention(block_id, "link_preview", &plain_text);
+                    return Ok(None);
+                }
+                Mention::LinkMention { link_mention } => {
                     let component = jarkup_rs::Text {
                         id: None,
                         props: jarkup_rs::TextProps {
-                            text: equation.expression,
-                            katex: Some(true),
+                            text: plain_text,
+                            favicon: self
+                                .fetch_favicon_by_url(context, block_id, &link_mention.href)
+                                .await,
+                            href: Some(link_mention.href),
                             ..Default::default()
                         },
                         slots: None,
                     };
 
-                    Ok(component.into())
+                    component.into()
                 }
-            };
+                Mention::TemplateMention {
+                    template_mention: _,
+                } => {
+                    context.insert_dropped_mention(block_id, "template_mention", &plain_text);
+                    return Ok(None);
+                }
+                Mention::Page { page: _ } => {
+                    context.insert_dropped_mention(block_id, "page", &plain_text);
+                    return Ok(None);
+                }
+                Mention::Database { database: _ } => {
+                    context.insert_dropped_mention(block_id, "database", &plain_text);
+                    return Ok(None);
+                }
+                Mention::CustomEmoji { custom_emoji } => {
+                    let component = jarkup_rs::Icon {
+                        id: Some(custom_emoji.id),
+                        props: jarkup_rs::IconProps {
+                            src: custom_emoji.url,
+                            alt: Some(custom_emoji.name),
+                        },
+                        slots: None,
+                    };
+
+                    component.into()
+                }
+            },
+            RichText::Equation {
+                equation,
+                annotations: _annotations,
+                plain_text: _plain_text,
+                href: _href,
+            } => {
+                let component = jarkup_rs::Text {
+                    id: None,
+                    props: jarkup_rs::TextProps {
+                        text: equation.expression,
+                        katex: Some(true),
+                        ..Default::default()
+                    },
+                    slots: None,
+                };
+
+                component.into()
+            }
+        };
+
+        Ok(Some(component))
+    }
+
+    /// Fetches the page at `url` and scrapes its bookmark metadata.
+    ///
+    /// Relative image and favicon URLs are resolved against the final URL after redirects.
+    pub async fn fetch_bookmark_metadata(
+        &self,
+        url: &str,
+    ) -> Result<crate::bookmark::BookmarkMetadata, crate::error::Error> {
+        let response = self
+            .reqwest_client
+            .get(url)
+            .header("user-agent", &self.options.network.user_agent)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let final_url = response.url().clone();
+        let html = response.text().await?;
+
+        let mut metadata = crate::bookmark::BookmarkMetadata::from_html(&html, &final_url);
+
+        let declared_favicon = metadata
+            .favicon
+            .as_deref()
+            .and_then(|favicon| url::Url::parse(favicon).ok());
 
-            components.push(component?);
+        metadata.favicon = self.resolve_favicon(declared_favicon, &final_url).await;
+
+        Ok(metadata)
+    }
+
+    /// Fetches the favicon of the page at `url`, recording failed requests in the report.
+    pub(crate) async fn fetch_favicon_by_url(
+        &self,
+        context: &crate::context::ConversionContext,
+        block_id: &str,
+        url: &str,
+    ) -> Option<String> {
+        if !self.options.network.fetch_favicons {
+            return None;
+        }
+
+        match context
+            .outbound_request(self.try_fetch_favicon_by_url(url))
+            .await
+        {
+            Ok(favicon) => favicon,
+            Err(error) if error.is_stopped() => None,
+            Err(error) => {
+                context.insert_metadata_failure(
+                    block_id,
+                    url,
+                    crate::report::MetadataKind::Favicon,
+                    &error.to_string(),
+                );
+                None
+            }
+        }
+    }
+
+    async fn try_fetch_favicon_by_url(
+        &self,
+        url: &str,
+    ) -> Result<Option<String>, crate::error::Error> {
+        let res = self
+            .reqwest_client
+            .get(url)
+            .header("user-agent", &self.options.network.user_agent)
+            .send()
+            .await?;
+        let page_url = res.url().clone();
+        let html = res.text().await?;
+        // `scraper::Html` is not `Send`, so it must be dropped before the next `.await`.
+        let declared_favicon = {
+            let document = scraper::Html::parse_document(&html);
+            let base_url = crate::favicon::base_url(&document, &page_url);
+            crate::favicon::find_best_icon(&document, &base_url)
+        };
+        Ok(self.resolve_favicon(declared_favicon, &page_url).await)
+    }
+
+    /// Falls back to `/favicon.ico` (if a HEAD request succeeds) when the page
+    /// declares no icon, and inlines small icons when `inline_favicon_max_bytes` is set.
+    async fn resolve_favicon(
+        &self,
+        declared_favicon: Option<url::Url>,
+        page_url: &url::Url,
+    ) -> Option<String> {
+        let favicon_url = match declared_favicon {
+            Some(favicon_url) => favicon_url,
+            None => {
+                let fallback_url = crate::favicon::fallback_url(page_url)?;
+                let res = self
+                    .reqwest_client
+                    .head(fallback_url.as_str())
+                    .header("user-agent", &self.options.network.user_agent)
+                    .send()
+                    .await
+                    .ok()?;
+
+                if !res.status().is_success() {
+                    return None;
+                }
+
+                fallback_url
+            }
+        };
+
+        if let Some(max_bytes) = self.options.network.inline_favicon_max_bytes
+            && favicon_url.scheme() != "data"
+            && let Some(data_url) = self.fetch_data_url(&favicon_url, max_bytes).await
+        {
+            return Some(data_url);
         }
 
-        return Ok(components);
+        Some(favicon_url.to_string())
+    }
+
+    /// Copies a Notion-hosted file to the asset store and returns its public URL.
+    /// Without an asset store, the (expiring) Notion URL is returned as is.
+    /// If the conversion has stopped, the Notion URL is returned as well.
+    async fn mirror_asset(
+        &self,
+        context: &crate::context::ConversionContext,
+        block_id: &str,
+        url: &str,
+    ) -> Result<String, crate::error::Error> {
+        let (src, _) = self.mirror_asset_with_bytes(context, block_id, url).await?;
+        Ok(src)
+    }
+
+    /// Like `mirror_asset`, but also returns the downloaded file, if it was
+    /// downloaded, so that it can be analysed without fetching it again.
+    async fn mirror_asset_with_bytes(
+        &self,
+        context: &crate::context::ConversionContext,
+        block_id: &str,
+        url: &str,
+    ) -> Result<(String, Option<Vec<u8>>), crate::error::Error> {
+        let Some(asset_store) = &self.asset_store else {
+            context.insert_uncacheable(block_id);
+            return Ok((url.to_owned(), None));
+        };
+
+        match context
+            .outbound_request(self.store_asset(asset_store.as_ref(), block_id, url))
+            .await
+        {
+            Ok((src, bytes)) => Ok((src, Some(bytes))),
+            Err(error) if error.is_stopped() => {
+                context.insert_uncacheable(block_id);
+                Ok((url.to_owned(), None))
+            }
+            Err(error) => Err(error),
+        }
     }
 
-    pub(crate) async fn fetch_favicon_by_url(&self, url: &str) -> Option<String> {
+    async fn store_asset(
+        &self,
+        asset_store: &dyn crate::asset::AssetStore,
+        block_id: &str,
+        url: &str,
+    ) -> Result<(String, Vec<u8>), crate::error::Error> {
         let res = self
             .reqwest_client
             .get(url)
-            .header("user-agent", "notion-to-jarkup")
+            .header("user-agent", &self.options.network.user_agent)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        let content_type = res
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.split(';').next().unwrap_or(value).trim().to_owned());
+
+        let max_bytes = self.options.network.max_asset_bytes;
+        let bytes = crate::image::read_body(res, max_bytes)
+            .await?
+            .ok_or_else(|| crate::error::Error::TooLarge {
+                url: url.to_owned(),
+                max_bytes,
+            })?;
+
+        let src = asset_store
+            .store(crate::asset::Asset {
+                block_id,
+                url,
+                content_type: content_type.as_deref(),
+                bytes: &bytes,
+            })
+            .await?;
+
+        Ok((src, bytes))
+    }
+
+    /// Downloads an image with the image fetcher and analyses it.
+    async fn analyze_image(&self, src: &str) -> Option<crate::image::ImageInfo> {
+        let max_bytes = self.options.image_analysis.max_bytes;
+        let bytes = self.image_fetcher.fetch(src, max_bytes).await.ok()??;
+
+        self.analyze_image_bytes(bytes).await
+    }
+
+    /// Analyses a downloaded image, unless it is larger than `image_analysis.max_bytes`.
+    async fn analyze_image_bytes(&self, bytes: Vec<u8>) -> Option<crate::image::ImageInfo> {
+        if bytes.len() > self.options.image_analysis.max_bytes {
+            return None;
+        }
+
+        // Decoding and computing a placeholder is CPU-bound, so keep it off the runtime threads.
+        let placeholder = self.options.image_analysis.placeholder;
+        tokio::task::spawn_blocking(move || crate::image::analyze(&bytes, placeholder))
+            .await
+            .ok()?
+    }
+
+    /// Downloads an image and encodes it as a `data:` URL if it is at most `max_bytes` long.
+    async fn fetch_data_url(&self, url: &url::Url, max_bytes: usize) -> Option<String> {
+        use base64::Engine;
+
+        let res = self
+            .reqwest_client
+            .get(url.as_str())
+            .header("user-agent", &self.options.network.user_agent)
             .send()
             .await
             .ok()?;
-        let html = res.text().await.ok()?;
-        let parsed_url = url::Url::parse(url).ok()?;
-        let scheme = parsed_url.scheme();
-        let host = if let url::Host::Domain(domain) = parsed_url.host()? {
-            Some(domain.to_string())
-        } else {
-            None
-        }?;
-        let meta_scraper = html_meta_scraper::MetaScraper::new(&html);
-        let base_url = url::Url::parse(&format!("{scheme}://{host}",)).ok()?;
-        let favicon_url = base_url.join(&meta_scraper.favicon()?).ok()?.to_string();
-        Some(favicon_url)
+
+        if !res.status().is_success() {
+            return None;
+        }
+
+        let content_type = res
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .map(|value| value.trim().to_owned())
+            .unwrap_or_else(|| String::from("image/x-icon"));
+
+        if !content_type.starts_with("image/") {
+            return None;
+        }
+
+        let bytes = crate::image::read_body(res, max_bytes).await.ok()??;
+
+        Some(format!(
+            "data:{};base64,{}",
+            content_type,
+            base64::engine::general_purpose::STANDARD.encode(&bytes)
+        ))
+    }
+
+    /// Converts a Notion icon into an inline component:
+    /// emojis become text, custom emojis and files become an `Icon`.
+    /// Notion-hosted icon files go through the asset store, like images.
+    pub(crate) async fn convert_icon(
+        &self,
+        context: &crate::context::ConversionContext,
+        block_id: &str,
+        icon: notionrs_types::object::icon::Icon,
+    ) -> Result<Option<jarkup_rs::InlineComponent>, crate::error::Error> {
+        let component: jarkup_rs::InlineComponent = match icon {
+            notionrs_types::object::icon::Icon::Emoji(emoji) => jarkup_rs::Text {
+                id: None,
+                props: jarkup_rs::TextProps {
+                    text: emoji.emoji,
+                    ..Default::default()
+                },
+                slots: None,
+            }
+            .into(),
+            notionrs_types::object::icon::Icon::CustomEmoji(custom_emoji) => jarkup_rs::Icon {
+                id: Some(custom_emoji.custom_emoji.id),
+                props: jarkup_rs::IconProps {
+                    src: custom_emoji.custom_emoji.url,
+                    alt: Some(custom_emoji.custom_emoji.name),
+                },
+                slots: None,
+            }
+            .into(),
+            notionrs_types::object::icon::Icon::File(file) => {
+                let src = match file {
+                    notionrs_types::object::file::File::External(external_file) => {
+                        external_file.external.url
+                    }
+                    notionrs_types::object::file::File::NotionHosted(notion_hosted_file) => {
+                        self.mirror_asset(context, block_id, &notion_hosted_file.file.url)
+                            .await?
+                    }
+                    _ => return Ok(None),
+                };
+
+                jarkup_rs::Icon {
+                    id: None,
+                    props: jarkup_rs::IconProps { src, alt: None },
+                    slots: None,
+                }
+                .into()
+            }
+        };
+
+        Ok(Some(component))
     }
 
     pub(crate) async fn convert_heading_block(
         &self,
+        context: &crate::context::ConversionContext,
         heading_block: HeadingBlock,
         block_id: &str,
         level: jarkup_rs::HeadingLevel,
         children: Vec<jarkup_rs::Component>,
-    ) -> Result<Option<jarkup_rs::Component>, crate::error::Error> {
-        let component: jarkup_rs::Component = if heading_block.is_toggleable {
-            jarkup_rs::Toggle {
+    ) -> Result<Vec<jarkup_rs::Component>, crate::error::Error> {
+        let inline_components = self
+            .convert_colored_rich_text(
+                context,
+                block_id,
+                heading_block.rich_text,
+                heading_block.color,
+            )
+            .await?;
+
+        if heading_block.is_toggleable
+            && self.options.toggleable_heading == crate::options::ToggleableHeadingStyle::Toggle
+        {
+            context.insert_toggle_heading(block_id.to_owned(), level);
+
+            let component = jarkup_rs::Toggle {
                 id: Some(block_id.to_owned()),
                 props: None,
                 slots: jarkup_rs::ToggleSlots {
                     default: children,
-                    summary: self.convert_rich_text(heading_block.rich_text).await?,
-                },
-            }
-            .into()
-        } else {
-            jarkup_rs::Heading {
-                id: Some(block_id.to_owned()),
-                props: jarkup_rs::HeadingProps { level },
-                slots: jarkup_rs::HeadingSlots {
-                    default: self.convert_rich_text(heading_block.rich_text).await?,
+                    summary: inline_components,
                 },
-            }
-            .into()
+            };
+
+            return Ok(vec![component.into()]);
+        }
+
+        let component = jarkup_rs::Heading {
+            id: Some(block_id.to_owned()),
+            props: jarkup_rs::HeadingProps { level },
+            slots: jarkup_rs::HeadingSlots {
+                default: inline_components,
+            },
         };
 
-        Ok(Some(component))
+        Ok(std::iter::once(component.into()).chain(children).collect())
     }
 
     pub(crate) fn is_kbd(&self, plain_text: &str, is_code: bool) -> bool {
@@ -1001,6 +2063,6 @@ impl Client {
             return true;
         };
 
-        return false;
+        false
     }
 }
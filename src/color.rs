@@ -0,0 +1,214 @@
+use notionrs_types::object::color::Color;
+use std::collections::HashMap;
+
+/// How Notion colors are written to `color` / `backgroundColor` props.
+///
+/// ```json
+/// { "mode": "light_dark", "light": { "blue": "#2f5aa8" }, "dark": { "blue": "#8fb3ff" } }
+/// ```
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ColorTheme {
+    /// Notion-like hex values.
+    #[default]
+    Notion,
+
+    /// The Notion color name itself (`blue`, `blue_background`), so that the
+    /// stylesheet decides the actual color.
+    Semantic,
+
+    /// Custom values keyed by Notion color name. Colors missing from the
+    /// palette fall back to [`ColorTheme::Notion`].
+    Custom { palette: ColorPalette },
+
+    /// A light/dark pair, written as CSS `light-dark(<light>, <dark>)`.
+    /// Colors missing from `light` fall back to [`ColorTheme::Notion`];
+    /// colors missing from `dark` use the light value alone.
+    LightDark {
+        light: ColorPalette,
+        dark: ColorPalette,
+    },
+}
+
+/// Color values keyed by Notion color name, e.g. `"blue"` or `"blue_background"`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(transparent)]
+pub struct ColorPalette(pub HashMap<String, String>);
+
+impl ColorPalette {
+    pub fn get(&self, color: Color) -> Option<&str> {
+        self.0.get(&color.to_string()).map(String::as_str)
+    }
+
+    pub fn insert(&mut self, color: Color, value: impl Into<String>) -> Option<String> {
+        self.0.insert(color.to_string(), value.into())
+    }
+}
+
+impl ColorTheme {
+    /// Resolves a text color. Returns `None` for `default` and background colors.
+    pub fn foreground(&self, color: Color) -> Option<String> {
+        if !Self::is_foreground(color) {
+            return None;
+        }
+
+        self.resolve(color)
+    }
+
+    /// Resolves a background color. Returns `None` for `default` and text colors.
+    pub fn background(&self, color: Color) -> Option<String> {
+        if !Self::is_background(color) {
+            return None;
+        }
+
+        self.resolve(color)
+    }
+
+    fn resolve(&self, color: Color) -> Option<String> {
+        match self {
+            ColorTheme::Notion => Self::notion(color).map(String::from),
+            ColorTheme::Semantic => Some(color.to_string()),
+            ColorTheme::Custom { palette } => palette
+                .get(color)
+                .or_else(|| Self::notion(color))
+                .map(String::from),
+            ColorTheme::LightDark { light, dark } => {
+                let light_value = light.get(color).or_else(|| Self::notion(color))?;
+
+                match dark.get(color) {
+                    Some(dark_value) => Some(format!("light-dark({light_value}, {dark_value})")),
+                    None => Some(light_value.to_owned()),
+                }
+            }
+        }
+    }
+
+    fn is_foreground(color: Color) -> bool {
+        matches!(
+            color,
+            Color::Blue
+                | Color::Brown
+                | Color::Gray
+                | Color::Green
+                | Color::Orange
+                | Color::Pink
+                | Color::Purple
+                | Color::Red
+                | Color::Yellow
+        )
+    }
+
+    fn is_background(color: Color) -> bool {
+        matches!(
+            color,
+            Color::BlueBackground
+                | Color::BrownBackground
+                | Color::GrayBackground
+                | Color::GreenBackground
+                | Color::OrangeBackground
+                | Color::PinkBackground
+                | Color::PurpleBackground
+                | Color::RedBackground
+                | Color::YellowBackground
+        )
+    }
+
+    fn notion(color: Color) -> Option<&'static str> {
+        match color {
+            Color::Blue => Some("#6987b8"),
+            Color::Brown => Some("#8b4c3f"),
+            Color::Gray => Some("#868e9c"),
+            Color::Green => Some("#59b57c"),
+            Color::Orange => Some("#bf7e71"),
+            Color::Pink => Some("#c9699e"),
+            Color::Purple => Some("#9771bd"),
+            Color::Red => Some("#b36472"),
+            Color::Yellow => Some("#b8a36e"),
+            Color::BlueBackground => Some("#c0cce1"),
+            Color::BrownBackground => Some("#d0bdac"),
+            Color::GrayBackground => Some("#cccfd5"),
+            Color::GreenBackground => Some("#b1dcc2"),
+            Color::OrangeBackground => Some("#f1dbd2"),
+            Color::PinkBackground => Some("#ebc7db"),
+            Color::PurpleBackground => Some("#d7c8e5"),
+            Color::RedBackground => Some("#e8c2c2"),
+            Color::YellowBackground => Some("#f0e9d7"),
+            Color::Default | Color::DefaultBackground => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn palette(entries: &[(Color, &str)]) -> ColorPalette {
+        let mut palette = ColorPalette::default();
+        for (color, value) in entries {
+            palette.insert(*color, *value);
+        }
+        palette
+    }
+
+    #[test]
+    fn semantic_writes_color_names() {
+        let theme = ColorTheme::Semantic;
+
+        assert_eq!(theme.foreground(Color::Blue).as_deref(), Some("blue"));
+        assert_eq!(
+            theme.background(Color::BlueBackground).as_deref(),
+            Some("blue_background")
+        );
+        assert_eq!(theme.foreground(Color::Default), None);
+        assert_eq!(theme.background(Color::DefaultBackground), None);
+        assert_eq!(theme.foreground(Color::BlueBackground), None);
+        assert_eq!(theme.background(Color::Blue), None);
+    }
+
+    #[test]
+    fn custom_falls_back_to_notion() {
+        let theme = ColorTheme::Custom {
+            palette: palette(&[(Color::Red, "#ff0000"), (Color::RedBackground, "#ffeeee")]),
+        };
+
+        assert_eq!(theme.foreground(Color::Red).as_deref(), Some("#ff0000"));
+        assert_eq!(
+            theme.background(Color::RedBackground).as_deref(),
+            Some("#ffeeee")
+        );
+        assert_eq!(theme.foreground(Color::Blue).as_deref(), Some("#6987b8"));
+        assert_eq!(
+            theme.background(Color::BlueBackground).as_deref(),
+            Some("#c0cce1")
+        );
+        assert_eq!(theme.foreground(Color::Default), None);
+        assert_eq!(theme.background(Color::DefaultBackground), None);
+    }
+
+    #[test]
+    fn light_dark_pairs_values() {
+        let theme = ColorTheme::LightDark {
+            light: palette(&[(Color::Blue, "#2f5aa8"), (Color::Green, "#1d7a46")]),
+            dark: palette(&[(Color::Blue, "#8fb3ff"), (Color::GrayBackground, "#333333")]),
+        };
+
+        assert_eq!(
+            theme.foreground(Color::Blue).as_deref(),
+            Some("light-dark(#2f5aa8, #8fb3ff)")
+        );
+        // Missing from `dark`: the light value alone.
+        assert_eq!(theme.foreground(Color::Green).as_deref(), Some("#1d7a46"));
+        // Missing from `light`: the Notion value, paired with `dark`.
+        assert_eq!(
+            theme.background(Color::GrayBackground).as_deref(),
+            Some("light-dark(#cccfd5, #333333)")
+        );
+        assert_eq!(
+            theme.background(Color::PinkBackground).as_deref(),
+            Some("#ebc7db")
+        );
+        assert_eq!(theme.foreground(Color::Default), None);
+        assert_eq!(theme.background(Color::DefaultBackground), None);
+    }
+}
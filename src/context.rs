@@ -0,0 +1,530 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use futures::future::Either;
+
+/// State shared by the blocks of a single conversion.
+#[derive(Debug, Default)]
+pub(crate) struct ConversionContext {
+    /// Numbered list items that start a Notion list, keyed by block id.
+    list_starts: Mutex<HashMap<String, crate::list::ListStart>>,
+
+    /// Results of image analysis, keyed by block id.
+    images: Mutex<HashMap<String, crate::image::ImageInfo>>,
+
+    /// Levels of toggleable headings converted to toggles, keyed by block id.
+    toggle_headings: Mutex<HashMap<String, jarkup_rs::HeadingLevel>>,
+
+    /// Metadata of bookmarked pages, keyed by block id.
+    bookmarks: Mutex<HashMap<String, crate::bookmark::BookmarkMetadata>>,
+
+    /// Ids of the blocks fetched so far, to detect cycles.
+    visited: Mutex<HashSet<String>>,
+
+    /// Whether subtrees are looked up in the block cache before they are fetched.
+    reuses_cached_subtrees: bool,
+
+    /// Subtrees found unchanged in the block cache, keyed by block id.
+    cached_subtrees: Mutex<HashMap<String, std::sync::Arc<crate::cache::CachedSubtree>>>,
+
+    /// Blocks whose converted form must not be cached, such as files left at
+    /// their expiring Notion URL.
+    uncacheable: Mutex<HashSet<String>>,
+
+    report: Mutex<crate::report::ConversionReport>,
+
+    cancellation: crate::cancel::CancellationToken,
+
+    /// The earlier of the token deadline and `limits.timeout_ms`.
+    deadline: Option<std::time::Instant>,
+
+    max_api_calls: Option<usize>,
+
+    max_outbound_requests: Option<usize>,
+
+    /// Caps the downloads in flight across the whole conversion at
+    /// `network.concurrency_limit`.
+    outbound_permits: Option<tokio::sync::Semaphore>,
+
+    stop_reason: Mutex<Option<crate::report::StopReason>>,
+}
+
+impl ConversionContext {
+    pub(crate) fn new(
+        options: &crate::options::ConversionOptions,
+        cancellation: &crate::cancel::CancellationToken,
+        started: std::time::Instant,
+    ) -> Self {
+        let limits = &options.limits;
+        let timeout_deadline = limits
+            .timeout_ms
+            .map(|timeout_ms| started + std::time::Duration::from_millis(timeout_ms));
+
+        Self {
+            cancellation: cancellation.clone(),
+            deadline: cancellation
+                .deadline()
+                .into_iter()
+                .chain(timeout_deadline)
+                .min(),
+            max_api_calls: limits.max_api_calls,
+            max_outbound_requests: limits.max_outbound_requests,
+            outbound_permits: Some(tokio::sync::Semaphore::new(
+                options.network.concurrency_limit.max(1),
+            )),
+            ..Self::default()
+        }
+    }
+
+    /// Looks subtrees up in the block cache while fetching.
+    pub(crate) fn reuse_cached_subtrees(&mut self) {
+        self.reuses_cached_subtrees = true;
+    }
+
+    pub(crate) fn reuses_cached_subtrees(&self) -> bool {
+        self.reuses_cached_subtrees
+    }
+
+    pub(crate) fn insert_list_start(&self, block_id: String, list_start: crate::list::ListStart) {
+        self.list_starts
+            .lock()
+            .expect("list_starts lock poisoned")
+            .insert(block_id, list_start);
+    }
+
+    pub(crate) fn is_list_start(&self, block_id: &str) -> bool {
+        self.list_starts
+            .lock()
+            .expect("list_starts lock poisoned")
+            .contains_key(block_id)
+    }
+
+    pub(crate) fn take_list_starts(&self) -> HashMap<String, crate::list::ListStart> {
+        std::mem::take(&mut *self.list_starts.lock().expect("list_starts lock poisoned"))
+    }
+
+    pub(crate) fn insert_image(&self, block_id: String, image_info: crate::image::ImageInfo) {
+        self.images
+            .lock()
+            .expect("images lock poisoned")
+            .insert(block_id, image_info);
+    }
+
+    pub(crate) fn take_images(&self) -> HashMap<String, crate::image::ImageInfo> {
+        std::mem::take(&mut *self.images.lock().expect("images lock poisoned"))
+    }
+
+    pub(crate) fn insert_toggle_heading(&self, block_id: String, level: jarkup_rs::HeadingLevel) {
+        self.toggle_headings
+            .lock()
+            .expect("toggle_headings lock poisoned")
+            .insert(block_id, level);
+    }
+
+    pub(crate) fn take_toggle_headings(&self) -> HashMap<String, jarkup_rs::HeadingLevel> {
+        std::mem::take(
+            &mut *self
+                .toggle_headings
+                .lock()
+                .expect("toggle_headings lock poisoned"),
+        )
+    }
+
+    pub(crate) fn insert_bookmark(
+        &self,
+        block_id: String,
+        metadata: crate::bookmark::BookmarkMetadata,
+    ) {
+        self.bookmarks
+            .lock()
+            .expect("bookmarks lock poisoned")
+            .insert(block_id, metadata);
+    }
+
+    pub(crate) fn take_bookmarks(&self) -> HashMap<String, crate::bookmark::BookmarkMetadata> {
+        std::mem::take(&mut *self.bookmarks.lock().expect("bookmarks lock poisoned"))
+    }
+
+    pub(crate) fn insert_failure(&self, block_id: &str, message: &str) {
+        self.report
+            .lock()
+            .expect("report lock poisoned")
+            .failures
+            .push(crate::report::BlockFailure {
+                block_id: block_id.to_owned(),
+                message: message.to_owned(),
+            });
+    }
+
+    pub(crate) fn insert_unsupported_block(&self, block_id: &str, block_type: &str) {
+        self.report
+            .lock()
+            .expect("report lock poisoned")
+            .unsupported_blocks
+            .push(crate::report::UnsupportedBlock {
+                block_id: block_id.to_owned(),
+                block_type: block_type.to_owned(),
+            });
+    }
+
+    pub(crate) fn insert_dropped_mention(
+        &self,
+        block_id: &str,
+        mention_type: &str,
+        plain_text: &str,
+    ) {
+        self.report
+            .lock()
+            .expect("report lock poisoned")
+            .dropped_mentions
+            .push(crate::report::DroppedMention {
+                block_id: block_id.to_owned(),
+                mention_type: mention_type.to_owned(),
+                plain_text: plain_text.to_owned(),
+            });
+    }
+
+    pub(crate) fn insert_metadata_failure(
+        &self,
+        block_id: &str,
+        url: &str,
+        kind: crate::report::MetadataKind,
+        message: &str,
+    ) {
+        self.report
+            .lock()
+            .expect("report lock poisoned")
+            .metadata_failures
+            .push(crate::report::MetadataFailure {
+                block_id: block_id.to_owned(),
+                url: url.to_owned(),
+                kind,
+                message: message.to_owned(),
+            });
+    }
+
+    /// Marks `block_id` as fetched. Returns `false` if it already was.
+    pub(crate) fn visit(&self, block_id: &str) -> bool {
+        self.visited
+            .lock()
+            .expect("visited lock poisoned")
+            .insert(block_id.to_owned())
+    }
+
+    pub(crate) fn insert_truncation(
+        &self,
+        block_id: &str,
+        reason: crate::report::TruncationReason,
+    ) {
+        self.report
+            .lock()
+            .expect("report lock poisoned")
+            .truncations
+            .push(crate::report::Truncation {
+                block_id: block_id.to_owned(),
+                reason,
+            });
+    }
+
+    /// Records a cache hit for `block_id` and marks the blocks below it as
+    /// fetched.
+    pub(crate) fn insert_cached_subtree(
+        &self,
+        block_id: String,
+        subtree: crate::cache::CachedSubtree,
+    ) {
+        {
+            let mut visited = self.visited.lock().expect("visited lock poisoned");
+            visited.extend(subtree.block_ids.iter().cloned());
+        }
+
+        self.report.lock().expect("report lock poisoned").cache_hits += 1;
+
+        self.cached_subtrees
+            .lock()
+            .expect("cached_subtrees lock poisoned")
+            .insert(block_id, std::sync::Arc::new(subtree));
+    }
+
+    pub(crate) fn cached_subtree(
+        &self,
+        block_id: &str,
+    ) -> Option<std::sync::Arc<crate::cache::CachedSubtree>> {
+        self.cached_subtrees
+            .lock()
+            .expect("cached_subtrees lock poisoned")
+            .get(block_id)
+            .cloned()
+    }
+
+    /// Records what the conversion of a cached subtree had recorded.
+    pub(crate) fn restore_cached_subtree(&self, subtree: &crate::cache::CachedSubtree) {
+        self.list_starts
+            .lock()
+            .expect("list_starts lock poisoned")
+            .extend(subtree.list_starts.clone());
+        self.images
+            .lock()
+            .expect("images lock poisoned")
+            .extend(subtree.images.clone());
+        self.toggle_headings
+            .lock()
+            .expect("toggle_headings lock poisoned")
+            .extend(subtree.toggle_headings.clone());
+        self.bookmarks
+            .lock()
+            .expect("bookmarks lock poisoned")
+            .extend(subtree.bookmarks.clone());
+
+        let mut report = self.report.lock().expect("report lock poisoned");
+        report
+            .unsupported_blocks
+            .extend(subtree.unsupported_blocks.iter().cloned());
+        report
+            .dropped_mentions
+            .extend(subtree.dropped_mentions.iter().cloned());
+        report
+            .truncations
+            .extend(subtree.truncations.iter().cloned());
+    }
+
+    /// Marks `block_id` as not cacheable, along with every subtree containing it.
+    pub(crate) fn insert_uncacheable(&self, block_id: &str) {
+        self.uncacheable
+            .lock()
+            .expect("uncacheable lock poisoned")
+            .insert(block_id.to_owned());
+    }
+
+    /// The cache entry for `components`, the converted blocks `block_ids`.
+    /// `None` if the subtree must not be cached: the conversion stopped, or
+    /// one of the blocks failed, was cut off, or is not cacheable.
+    pub(crate) fn create_cached_subtree(
+        &self,
+        last_edited_time: String,
+        fingerprint: String,
+        components: Vec<jarkup_rs::Component>,
+        block_ids: Vec<String>,
+    ) -> Option<crate::cache::CachedSubtree> {
+        if self.stop_reason().is_some() {
+            return None;
+        }
+
+        let ids: HashSet<&str> = block_ids.iter().map(String::as_str).collect();
+        let contains = |block_id: &String| ids.contains(block_id.as_str());
+
+        if self
+            .uncacheable
+            .lock()
+            .expect("uncacheable lock poisoned")
+            .iter()
+            .any(contains)
+        {
+            return None;
+        }
+
+        let report = self.report.lock().expect("report lock poisoned");
+
+        if report
+            .failures
+            .iter()
+            .any(|failure| contains(&failure.block_id))
+            || report
+                .metadata_failures
+                .iter()
+                .any(|failure| contains(&failure.block_id))
+            || report.truncations.iter().any(|truncation| {
+                contains(&truncation.block_id)
+                    && truncation.reason != crate::report::TruncationReason::DepthLimit
+            })
+        {
+            return None;
+        }
+
+        fn select<T: Clone>(
+            map: &Mutex<HashMap<String, T>>,
+            contains: impl Fn(&String) -> bool,
+        ) -> HashMap<String, T> {
+            map.lock()
+                .expect("side table lock poisoned")
+                .iter()
+                .filter(|(block_id, _)| contains(block_id))
+                .map(|(block_id, value)| (block_id.clone(), value.clone()))
+                .collect()
+        }
+
+        Some(crate::cache::CachedSubtree {
+            last_edited_time,
+            fingerprint,
+            components,
+            list_starts: select(&self.list_starts, contains),
+            images: select(&self.images, contains),
+            toggle_headings: select(&self.toggle_headings, contains),
+            bookmarks: select(&self.bookmarks, contains),
+            unsupported_blocks: report
+                .unsupported_blocks
+                .iter()
+                .filter(|entry| contains(&entry.block_id))
+                .cloned()
+                .collect(),
+            dropped_mentions: report
+                .dropped_mentions
+                .iter()
+                .filter(|entry| contains(&entry.block_id))
+                .cloned()
+                .collect(),
+            truncations: report
+                .truncations
+                .iter()
+                .filter(|entry| contains(&entry.block_id))
+                .cloned()
+                .collect(),
+            block_ids,
+        })
+    }
+
+    pub(crate) fn insert_cache_failure(
+        &self,
+        block_id: &str,
+        operation: crate::report::CacheOperation,
+        message: &str,
+    ) {
+        self.report
+            .lock()
+            .expect("report lock poisoned")
+            .cache_failures
+            .push(crate::report::CacheFailure {
+                block_id: block_id.to_owned(),
+                operation,
+                message: message.to_owned(),
+            });
+    }
+
+    /// Why the conversion has to stop, if it has.
+    pub(crate) fn stop_reason(&self) -> Option<crate::report::StopReason> {
+        let mut stop_reason = self.stop_reason.lock().expect("stop_reason lock poisoned");
+
+        if stop_reason.is_none() {
+            if self.cancellation.is_cancelled() {
+                *stop_reason = Some(crate::report::StopReason::Cancelled);
+            } else if self
+                .deadline
+                .is_some_and(|deadline| std::time::Instant::now() >= deadline)
+            {
+                *stop_reason = Some(crate::report::StopReason::Deadline);
+            }
+        }
+
+        *stop_reason
+    }
+
+    /// Why the conversion stopped, if a call was refused or abandoned because of it.
+    pub(crate) fn recorded_stop_reason(&self) -> Option<crate::report::StopReason> {
+        *self.stop_reason.lock().expect("stop_reason lock poisoned")
+    }
+
+    /// Records `reason` unless the conversion already stopped, and returns the first reason.
+    fn stop(&self, reason: crate::report::StopReason) -> crate::report::StopReason {
+        *self
+            .stop_reason
+            .lock()
+            .expect("stop_reason lock poisoned")
+            .get_or_insert(reason)
+    }
+
+    /// Runs a Notion API call, unless the conversion has stopped or the call
+    /// budget is exhausted.
+    pub(crate) async fn api_call<T>(
+        &self,
+        call: impl Future<Output = Result<T, crate::error::Error>>,
+    ) -> Result<T, crate::error::Error> {
+        self.start_request(
+            |report| &mut report.api_calls,
+            self.max_api_calls,
+            crate::report::StopReason::ApiCallBudget,
+        )?;
+
+        self.until_stopped(call).await
+    }
+
+    /// Runs a download, unless the conversion has stopped or the outbound
+    /// request budget is exhausted. Waits for a permit first if
+    /// `network.concurrency_limit` downloads are already in flight.
+    pub(crate) async fn outbound_request<T>(
+        &self,
+        request: impl Future<Output = Result<T, crate::error::Error>>,
+    ) -> Result<T, crate::error::Error> {
+        self.start_request(
+            |report| &mut report.outbound_requests,
+            self.max_outbound_requests,
+            crate::report::StopReason::OutboundRequestBudget,
+        )?;
+
+        self.until_stopped(async {
+            let _permit = match &self.outbound_permits {
+                Some(permits) => Some(permits.acquire().await.expect("semaphore is never closed")),
+                None => None,
+            };
+
+            request.await
+        })
+        .await
+    }
+
+    fn start_request(
+        &self,
+        counter: impl FnOnce(&mut crate::report::ConversionReport) -> &mut usize,
+        budget: Option<usize>,
+        budget_reason: crate::report::StopReason,
+    ) -> Result<(), crate::error::Error> {
+        if let Some(reason) = self.stop_reason() {
+            return Err(crate::error::Error::Stopped(reason));
+        }
+
+        let mut report = self.report.lock().expect("report lock poisoned");
+        let count = counter(&mut report);
+
+        if budget.is_some_and(|budget| *count >= budget) {
+            return Err(crate::error::Error::Stopped(self.stop(budget_reason)));
+        }
+
+        *count += 1;
+        Ok(())
+    }
+
+    /// Awaits `future`, giving up as soon as the token is cancelled or the deadline passes.
+    async fn until_stopped<T>(
+        &self,
+        future: impl Future<Output = Result<T, crate::error::Error>>,
+    ) -> Result<T, crate::error::Error> {
+        let future = std::pin::pin!(future);
+        let stopped = std::pin::pin!(self.stopped());
+
+        match futures::future::select(future, stopped).await {
+            Either::Left((result, _)) => result,
+            Either::Right((reason, _)) => Err(crate::error::Error::Stopped(reason)),
+        }
+    }
+
+    /// Completes when the token is cancelled or the deadline passes.
+    async fn stopped(&self) -> crate::report::StopReason {
+        let cancelled = std::pin::pin!(self.cancellation.cancelled());
+        let deadline = std::pin::pin!(async {
+            match self.deadline {
+                Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+                None => std::future::pending().await,
+            }
+        });
+
+        let reason = match futures::future::select(cancelled, deadline).await {
+            Either::Left(_) => crate::report::StopReason::Cancelled,
+            Either::Right(_) => crate::report::StopReason::Deadline,
+        };
+
+        self.stop(reason)
+    }
+
+    pub(crate) fn take_report(&self) -> crate::report::ConversionReport {
+        std::mem::take(&mut *self.report.lock().expect("report lock poisoned"))
+    }
+}
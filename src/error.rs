@@ -1,8 +1,131 @@
 #[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
+    /// A Notion API error without a more specific variant.
     #[error("notionrs error: {0}")]
-    NotionRs(#[from] notionrs::error::Error),
+    NotionRs(#[source] notionrs::error::Error),
 
+    /// An HTTP error without a more specific variant.
     #[error("reqwest error: {0}")]
-    Reqwest(#[from] reqwest::Error),
+    Reqwest(#[source] reqwest::Error),
+
+    /// Too many requests (HTTP 429). `retry_after` is always `None` for
+    /// `notionrs` errors, which do not carry the `Retry-After` header; custom
+    /// [`crate::source::BlockSource`] implementations may set it.
+    #[error("rate limited")]
+    RateLimited {
+        retry_after: Option<std::time::Duration>,
+    },
+
+    #[error("json error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// The object does not exist (HTTP 404). Notion also answers 404 for
+    /// pages and blocks that are not shared with the integration.
+    #[error("not found: {0}")]
+    NotFound(String),
+
+    /// The token is missing or invalid (HTTP 401).
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+
+    /// The integration may not access the object (HTTP 403).
+    #[error("restricted: {0}")]
+    Restricted(String),
+
+    /// The request was rejected as invalid (HTTP 400).
+    #[error("validation error: {0}")]
+    Validation(String),
+
+    /// The request timed out, on our side or on Notion's (HTTP 408/504).
+    #[error("timeout: {0}")]
+    Timeout(String),
+
+    /// A download was larger than allowed, e.g. by `network.max_asset_bytes`.
+    #[error("too large: {url} is over {max_bytes} bytes")]
+    TooLarge { url: String, max_bytes: usize },
+
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("client builder error: {0}")]
+    Builder(String),
+
+    /// The conversion was cancelled or hit a limit of
+    /// [`crate::options::LimitOptions`], and `limits.on_exceeded` is `fail`.
+    #[error("conversion stopped: {0}")]
+    Stopped(crate::report::StopReason),
+
+    /// An error that occurred while fetching or converting a block, with the
+    /// ids of the blocks leading to it, outermost first.
+    #[error("in block {}: {source}", path.join(" > "))]
+    Block {
+        path: Vec<String>,
+        #[source]
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// The ids of the blocks leading to the error, outermost first.
+    /// Empty if the error is not tied to a block.
+    pub fn block_path(&self) -> &[String] {
+        match self {
+            Error::Block { path, .. } => path,
+            _ => &[],
+        }
+    }
+
+    /// The error without its block path, for matching on the typed variants.
+    pub fn without_block_path(&self) -> &Error {
+        match self {
+            Error::Block { source, .. } => source,
+            error => error,
+        }
+    }
+
+    pub(crate) fn is_stopped(&self) -> bool {
+        matches!(self.without_block_path(), Error::Stopped(_))
+    }
+
+    /// Prepends `block_id` to the block path.
+    pub(crate) fn in_block(self, block_id: &str) -> Self {
+        match self {
+            Error::Block { mut path, source } => {
+                path.insert(0, block_id.to_owned());
+                Error::Block { path, source }
+            }
+            error => Error::Block {
+                path: vec![block_id.to_owned()],
+                source: Box::new(error),
+            },
+        }
+    }
+}
+
+impl From<notionrs::error::Error> for Error {
+    fn from(error: notionrs::error::Error) -> Self {
+        match error {
+            notionrs::error::Error::Http { status, message } => match status {
+                400 => Error::Validation(message),
+                401 => Error::Unauthorized(message),
+                403 => Error::Restricted(message),
+                404 => Error::NotFound(message),
+                408 | 504 => Error::Timeout(message),
+                429 => Error::RateLimited { retry_after: None },
+                _ => Error::NotionRs(notionrs::error::Error::Http { status, message }),
+            },
+            error => Error::NotionRs(error),
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            Error::Timeout(error.to_string())
+        } else {
+            Error::Reqwest(error)
+        }
+    }
 }
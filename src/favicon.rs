@@ -0,0 +1,114 @@
+/// The icon size (in CSS pixels) that favicons are displayed at next to links.
+const PREFERRED_SIZE: u32 = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum IconSize {
+    /// `sizes="any"`, typically an SVG icon.
+    Any,
+    /// The largest edge listed in `sizes`.
+    Pixels(u32),
+}
+
+#[derive(Debug, Clone)]
+struct IconLink {
+    href: url::Url,
+    size: Option<IconSize>,
+    is_apple_touch_icon: bool,
+}
+
+impl IconLink {
+    /// Lower is better. Icons close to `PREFERRED_SIZE` win; downscaling is
+    /// preferred over upscaling, and `apple-touch-icon` is a fallback.
+    fn score(&self) -> u32 {
+        let size_score = match self.size {
+            Some(IconSize::Any) => 0,
+            Some(IconSize::Pixels(size)) if size >= PREFERRED_SIZE => size - PREFERRED_SIZE,
+            Some(IconSize::Pixels(size)) => (PREFERRED_SIZE - size) * 4,
+            None if self.is_apple_touch_icon => 180 - PREFERRED_SIZE,
+            None => PREFERRED_SIZE / 2,
+        };
+
+        if self.is_apple_touch_icon {
+            size_score + PREFERRED_SIZE
+        } else {
+            size_score
+        }
+    }
+}
+
+/// Returns the URL relative links in `document` resolve against,
+/// honouring `<base href>`.
+pub(crate) fn base_url(document: &scraper::Html, page_url: &url::Url) -> url::Url {
+    let selector = scraper::Selector::parse("base[href]").expect("valid selector");
+
+    document
+        .select(&selector)
+        .filter_map(|element| element.value().attr("href"))
+        .find_map(|href| page_url.join(href.trim()).ok())
+        .unwrap_or_else(|| page_url.clone())
+}
+
+/// Picks the best icon declared with `rel="icon"`, `rel="shortcut icon"`
+/// or `rel="apple-touch-icon"`, resolved against `base_url`.
+pub(crate) fn find_best_icon(document: &scraper::Html, base_url: &url::Url) -> Option<url::Url> {
+    let selector = scraper::Selector::parse("link[rel][href]").expect("valid selector");
+
+    let mut icons: Vec<IconLink> = document
+        .select(&selector)
+        .filter_map(|element| {
+            let rel = element.value().attr("rel")?.to_ascii_lowercase();
+            let rel_tokens: Vec<&str> = rel.split_ascii_whitespace().collect();
+
+            let is_apple_touch_icon = rel_tokens
+                .iter()
+                .any(|t| *t == "apple-touch-icon" || *t == "apple-touch-icon-precomposed");
+
+            if !is_apple_touch_icon && !rel_tokens.contains(&"icon") {
+                return None;
+            }
+
+            let href = base_url.join(element.value().attr("href")?.trim()).ok()?;
+
+            if !matches!(href.scheme(), "http" | "https" | "data") {
+                return None;
+            }
+
+            Some(IconLink {
+                href,
+                size: element.value().attr("sizes").and_then(parse_sizes),
+                is_apple_touch_icon,
+            })
+        })
+        .collect();
+
+    // `sort_by_key` is stable, so document order breaks ties.
+    icons.sort_by_key(IconLink::score);
+
+    icons.into_iter().next().map(|icon| icon.href)
+}
+
+/// `/favicon.ico` on the same origin (scheme, host and port) as `page_url`.
+pub(crate) fn fallback_url(page_url: &url::Url) -> Option<url::Url> {
+    page_url.join("/favicon.ico").ok()
+}
+
+fn parse_sizes(sizes: &str) -> Option<IconSize> {
+    if sizes
+        .split_ascii_whitespace()
+        .any(|s| s.eq_ignore_ascii_case("any"))
+    {
+        return Some(IconSize::Any);
+    }
+
+    sizes
+        .split_ascii_whitespace()
+        .filter_map(|size| {
+            let (width, height) = size
+                .to_ascii_lowercase()
+                .split_once('x')
+                .map(|(w, h)| (w.parse::<u32>().ok(), h.parse::<u32>().ok()))?;
+            Some(width?.max(height?))
+        })
+        .max()
+        .map(IconSize::Pixels)
+}
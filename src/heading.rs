@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use crate::options::{HeadingLevelAdjustment, HeadingLevelOptions};
+
+/// Rewrites the level of every heading according to `options`, in document order.
+///
+/// Toggles listed in `toggle_headings` (toggleable headings, keyed by block id)
+/// count as headings; their levels are rewritten in the map.
+pub(crate) fn adjust_heading_levels(
+    components: &mut [jarkup_rs::Component],
+    toggle_headings: &mut HashMap<String, jarkup_rs::HeadingLevel>,
+    options: &HeadingLevelOptions,
+) {
+    if options.adjustment == HeadingLevelAdjustment::Keep && !options.fix_skips {
+        return;
+    }
+
+    let mut levels: Vec<u8> = Vec::new();
+
+    crate::visit::walk_mut(
+        components,
+        &mut |component| {
+            if let Some(level) = heading_level(component, toggle_headings) {
+                levels.push(level.clone().into());
+            }
+        },
+        &mut |_| {},
+    );
+
+    let Some(top_level) = levels.iter().min().copied() else {
+        return;
+    };
+
+    let offset = match options.adjustment {
+        HeadingLevelAdjustment::Keep => 0,
+        HeadingLevelAdjustment::Shift { offset } => i16::from(offset),
+        HeadingLevelAdjustment::Normalize { top_level: target } => {
+            i16::from(target.clamp(1, 6)) - i16::from(top_level)
+        }
+    };
+
+    let mut levels: Vec<u8> = levels
+        .into_iter()
+        .map(|level| (i16::from(level) + offset).clamp(1, 6) as u8)
+        .collect();
+
+    if options.fix_skips {
+        levels = close_level_skips(&levels);
+    }
+
+    let mut levels = levels.into_iter();
+
+    crate::visit::walk_mut(
+        components,
+        &mut |component| {
+            if let Some(heading_level) = heading_level_mut(component, toggle_headings)
+                && let Some(level) = levels.next()
+                && let Ok(level) = jarkup_rs::HeadingLevel::try_from(level)
+            {
+                *heading_level = level;
+            }
+        },
+        &mut |_| {},
+    );
+}
+
+/// The level of a heading, or of a toggle listed in `toggle_headings`.
+fn heading_level<'a>(
+    component: &'a jarkup_rs::Component,
+    toggle_headings: &'a HashMap<String, jarkup_rs::HeadingLevel>,
+) -> Option<&'a jarkup_rs::HeadingLevel> {
+    match component {
+        jarkup_rs::Component::BlockComponent(jarkup_rs::BlockComponent::Heading(heading)) => {
+            Some(&heading.props.level)
+        }
+        jarkup_rs::Component::BlockComponent(jarkup_rs::BlockComponent::Toggle(toggle)) => {
+            toggle_headings.get(toggle.id.as_deref()?)
+        }
+        _ => None,
+    }
+}
+
+fn heading_level_mut<'a>(
+    component: &'a mut jarkup_rs::Component,
+    toggle_headings: &'a mut HashMap<String, jarkup_rs::HeadingLevel>,
+) -> Option<&'a mut jarkup_rs::HeadingLevel> {
+    match component {
+        jarkup_rs::Component::BlockComponent(jarkup_rs::BlockComponent::Heading(heading)) => {
+            Some(&mut heading.props.level)
+        }
+        jarkup_rs::Component::BlockComponent(jarkup_rs::BlockComponent::Toggle(toggle)) => {
+            toggle_headings.get_mut(toggle.id.as_deref()?)
+        }
+        _ => None,
+    }
+}
+
+/// Makes every heading at most one level deeper than its parent section.
+/// The top level used on the page is kept.
+fn close_level_skips(levels: &[u8]) -> Vec<u8> {
+    let Some(top_level) = levels.iter().min().copied() else {
+        return Vec::new();
+    };
+
+    // (input level, output level) of the open sections.
+    let mut sections: Vec<(u8, u8)> = Vec::new();
+
+    levels
+        .iter()
+        .map(|&level| {
+            while sections.last().is_some_and(|&(open, _)| open >= level) {
+                sections.pop();
+            }
+
+            let output = sections
+                .last()
+                .map_or(top_level, |&(_, parent)| (parent + 1).min(6));
+
+            sections.push((level, output));
+            output
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adjusted(levels: &[u8], adjustment: HeadingLevelAdjustment, fix_skips: bool) -> Vec<u8> {
+        let mut components: Vec<jarkup_rs::Component> = levels
+            .iter()
+            .map(|&level| {
+                jarkup_rs::Heading {
+                    id: None,
+                    props: jarkup_rs::HeadingProps {
+                        level: jarkup_rs::HeadingLevel::try_from(level).expect("level"),
+                    },
+                    slots: jarkup_rs::HeadingSlots::default(),
+                }
+                .into()
+            })
+            .collect();
+
+        let options = HeadingLevelOptions {
+            adjustment,
+            fix_skips,
+        };
+        adjust_heading_levels(&mut components, &mut HashMap::new(), &options);
+
+        components
+            .iter()
+            .filter_map(|component| heading_level(component, &HashMap::new()).cloned())
+            .map(u8::from)
+            .collect()
+    }
+
+    #[test]
+    fn shift_clamps_at_h6() {
+        let levels = adjusted(
+            &[1, 2, 6],
+            HeadingLevelAdjustment::Shift { offset: 1 },
+            false,
+        );
+
+        assert_eq!(levels, [2, 3, 6]);
+    }
+
+    #[test]
+    fn shift_clamps_at_h1() {
+        let levels = adjusted(
+            &[1, 3, 4],
+            HeadingLevelAdjustment::Shift { offset: -2 },
+            false,
+        );
+
+        assert_eq!(levels, [1, 1, 2]);
+    }
+
+    #[test]
+    fn shift_then_fix_skips() {
+        let levels = adjusted(
+            &[1, 3, 3, 2],
+            HeadingLevelAdjustment::Shift { offset: 1 },
+            true,
+        );
+
+        assert_eq!(levels, [2, 3, 3, 3]);
+    }
+
+    #[test]
+    fn fix_skips_after_clamped_shift() {
+        let levels = adjusted(
+            &[2, 4, 6],
+            HeadingLevelAdjustment::Shift { offset: -3 },
+            true,
+        );
+
+        assert_eq!(levels, [1, 1, 2]);
+    }
+}
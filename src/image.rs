@@ -0,0 +1,191 @@
+use std::sync::Arc;
+
+type AltTextFn = dyn Fn(&str, &str) -> Option<String> + Send + Sync;
+
+/// Alt text callback set with [`crate::client::ClientBuilder::image_alt_text`].
+#[derive(Clone)]
+pub struct ImageAltText(Arc<AltTextFn>);
+
+impl ImageAltText {
+    pub fn new<F>(image_alt_text: F) -> Self
+    where
+        F: Fn(&str, &str) -> Option<String> + Send + Sync + 'static,
+    {
+        Self(Arc::new(image_alt_text))
+    }
+
+    /// Calls the callback with the image block id and URL.
+    pub fn alt_text(&self, block_id: &str, src: &str) -> Option<String> {
+        (self.0)(block_id, src)
+    }
+}
+
+impl std::fmt::Debug for ImageAltText {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ImageAltText").finish_non_exhaustive()
+    }
+}
+
+/// Downloads images for [`crate::options::ImageAnalysisOptions`]. Implement it
+/// to serve images from elsewhere, e.g. local bytes in tests.
+#[async_trait::async_trait]
+pub trait ImageFetcher: std::fmt::Debug + Send + Sync {
+    /// The bytes of the image, or `None` if it is larger than `max_bytes`.
+    /// Implementations should stop reading as soon as the limit is passed.
+    async fn fetch(
+        &self,
+        url: &str,
+        max_bytes: usize,
+    ) -> Result<Option<Vec<u8>>, crate::error::Error>;
+}
+
+/// The default [`ImageFetcher`], which downloads images over HTTP.
+#[derive(Debug, Clone)]
+pub struct ReqwestImageFetcher {
+    reqwest_client: reqwest::Client,
+    user_agent: String,
+}
+
+impl ReqwestImageFetcher {
+    pub fn new(reqwest_client: reqwest::Client, user_agent: impl Into<String>) -> Self {
+        Self {
+            reqwest_client,
+            user_agent: user_agent.into(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl ImageFetcher for ReqwestImageFetcher {
+    async fn fetch(
+        &self,
+        url: &str,
+        max_bytes: usize,
+    ) -> Result<Option<Vec<u8>>, crate::error::Error> {
+        let response = self
+            .reqwest_client
+            .get(url)
+            .header("user-agent", &self.user_agent)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        read_body(response, max_bytes).await
+    }
+}
+
+/// Reads a response body chunk by chunk. Returns `None` as soon as it turns out
+/// to be longer than `max_bytes`, whether or not `Content-Length` was sent.
+pub(crate) async fn read_body(
+    mut response: reqwest::Response,
+    max_bytes: usize,
+) -> Result<Option<Vec<u8>>, crate::error::Error> {
+    if response
+        .content_length()
+        .is_some_and(|length| length > max_bytes as u64)
+    {
+        return Ok(None);
+    }
+
+    let mut body = Vec::new();
+
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > max_bytes {
+            return Ok(None);
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(Some(body))
+}
+
+/// What image analysis found out about an image, which `jarkup_rs::ImageProps`
+/// cannot carry.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[non_exhaustive]
+pub struct ImageInfo {
+    pub width: u32,
+    pub height: u32,
+
+    /// A [BlurHash](https://blurha.sh) of the image.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blurhash: Option<String>,
+
+    /// A tiny PNG version of the image as a `data:` URL.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lqip: Option<String>,
+}
+
+/// Reads the dimensions of an image and computes the requested placeholder.
+/// Returns `None` if the format is not recognised.
+pub(crate) fn analyze(
+    bytes: &[u8],
+    placeholder: crate::options::ImagePlaceholder,
+) -> Option<ImageInfo> {
+    let size = imagesize::blob_size(bytes).ok()?;
+
+    let (blurhash, lqip) = match placeholder {
+        crate::options::ImagePlaceholder::None => (None, None),
+        crate::options::ImagePlaceholder::Blurhash => (blurhash(bytes), None),
+        crate::options::ImagePlaceholder::Lqip => (None, lqip(bytes)),
+    };
+
+    Some(ImageInfo {
+        width: u32::try_from(size.width).ok()?,
+        height: u32::try_from(size.height).ok()?,
+        blurhash,
+        lqip,
+    })
+}
+
+/// Images wider or higher than this are not decoded for a placeholder.
+const MAX_DECODED_DIMENSION: u32 = 10_000;
+
+/// Memory the decoder may allocate for a placeholder.
+const MAX_DECODED_ALLOC: u64 = 256 * 1024 * 1024;
+
+/// Decodes an image, refusing declared dimensions or allocations beyond the
+/// limits above, so that a small file cannot claim gigabytes of pixels.
+fn decode(bytes: &[u8]) -> Option<image::DynamicImage> {
+    let mut reader = image::ImageReader::new(std::io::Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()?;
+
+    let mut limits = image::Limits::default();
+    limits.max_image_width = Some(MAX_DECODED_DIMENSION);
+    limits.max_image_height = Some(MAX_DECODED_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODED_ALLOC);
+    reader.limits(limits);
+
+    reader.decode().ok()
+}
+
+fn blurhash(bytes: &[u8]) -> Option<String> {
+    // BlurHash only keeps a few components, so a small thumbnail is enough.
+    let thumbnail = decode(bytes)?.thumbnail(32, 32).to_rgba8();
+
+    blurhash::encode(
+        4,
+        3,
+        thumbnail.width(),
+        thumbnail.height(),
+        thumbnail.as_raw(),
+    )
+    .ok()
+}
+
+fn lqip(bytes: &[u8]) -> Option<String> {
+    use base64::Engine;
+
+    let thumbnail = decode(bytes)?.thumbnail(16, 16);
+
+    let mut png: Vec<u8> = Vec::new();
+    thumbnail
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .ok()?;
+
+    Some(format!(
+        "data:image/png;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(&png)
+    ))
+}
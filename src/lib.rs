@@ -1,2 +1,21 @@
+pub mod anchor;
+pub mod asset;
+pub mod bookmark;
+pub mod cache;
+pub mod cancel;
 pub mod client;
+pub mod color;
+pub(crate) mod context;
 pub mod error;
+pub(crate) mod favicon;
+pub(crate) mod heading;
+pub mod image;
+pub mod list;
+pub mod options;
+pub mod output;
+pub(crate) mod rate_limit;
+pub mod report;
+pub mod source;
+pub(crate) mod table;
+pub mod tree;
+pub(crate) mod visit;
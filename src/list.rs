@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+/// Numbering of an ordered list, which `jarkup_rs::ListProps` cannot carry.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[non_exhaustive]
+pub struct OrderedList {
+    /// The number of the first item.
+    pub start: u32,
+
+    /// How many ordered lists this list is nested in (0 at the top level).
+    pub depth: usize,
+
+    /// The numbering style: the one chosen in Notion, or Notion's default
+    /// for the depth (`1.`, `a.`, `i.`, then repeating).
+    pub format: NumberingFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum NumberingFormat {
+    Numbers,
+    Letters,
+    Roman,
+}
+
+impl NumberingFormat {
+    fn for_depth(depth: usize) -> Self {
+        match depth % 3 {
+            0 => NumberingFormat::Numbers,
+            1 => NumberingFormat::Letters,
+            _ => NumberingFormat::Roman,
+        }
+    }
+}
+
+impl From<notionrs_types::object::block::numbered_list_item::NumberedListFormat>
+    for NumberingFormat
+{
+    fn from(format: notionrs_types::object::block::numbered_list_item::NumberedListFormat) -> Self {
+        use notionrs_types::object::block::numbered_list_item::NumberedListFormat;
+
+        match format {
+            NumberedListFormat::Numbers => NumberingFormat::Numbers,
+            NumberedListFormat::Letters => NumberingFormat::Letters,
+            NumberedListFormat::Roman => NumberingFormat::Roman,
+        }
+    }
+}
+
+/// `list_start_index` / `list_format` of a numbered list item.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub(crate) struct ListStart {
+    pub(crate) start_index: Option<u32>,
+    pub(crate) format: Option<NumberingFormat>,
+}
+
+impl ListStart {
+    fn of(block: &notionrs_types::object::block::BlockResponse) -> Option<Self> {
+        match &block.block {
+            notionrs_types::object::block::Block::NumberedListItem { numbered_list_item } => {
+                Some(Self {
+                    start_index: numbered_list_item.list_start_index,
+                    format: numbered_list_item.list_format.clone().map(Into::into),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.start_index.is_none() && self.format.is_none()
+    }
+
+    /// Whether an item with these values can be the item at `position`
+    /// (0-based) of a list that started with `first`.
+    fn continues(&self, first: &ListStart, position: u32) -> bool {
+        let start = first.start_index.unwrap_or(1);
+
+        let same_format = self.format.is_none() || self.format == first.format;
+        let same_start = self.start_index.is_none() || self.start_index == Some(start + position);
+
+        same_format && same_start
+    }
+}
+
+/// Finds the numbered list items among `siblings` that start a Notion list
+/// and carry a start number or format, in order.
+///
+/// Notion sets `list_start_index` / `list_format` on the first item of a list,
+/// but an item that repeats the format of its list, or carries its own number,
+/// continues the list rather than restarting it. Any other start (e.g. "Start
+/// at" on an item in the middle, even with the value the list started at) or a
+/// different format starts a new list.
+pub(crate) fn find_list_starts<'a>(
+    siblings: impl IntoIterator<Item = &'a notionrs_types::object::block::BlockResponse>,
+) -> Vec<(String, ListStart)> {
+    let mut list_starts = Vec::new();
+
+    // The values of the first item of the current list, and its length so far.
+    let mut current: Option<(ListStart, u32)> = None;
+
+    for block in siblings {
+        let Some(list_start) = ListStart::of(block) else {
+            current = None;
+            continue;
+        };
+
+        match &mut current {
+            Some((first, length)) if list_start.continues(first, *length) => *length += 1,
+            _ => {
+                if !list_start.is_empty() {
+                    list_starts.push((block.id.clone(), list_start.clone()));
+                }
+                current = Some((list_start, 1));
+            }
+        }
+    }
+
+    list_starts
+}
+
+/// Describes every ordered list, keyed by the block id of its first item.
+pub(crate) fn describe_ordered_lists(
+    components: &[jarkup_rs::Component],
+    list_starts: &HashMap<String, ListStart>,
+) -> HashMap<String, OrderedList> {
+    let mut ordered_lists = HashMap::new();
+    collect_ordered_lists(components, 0, list_starts, &mut ordered_lists);
+    ordered_lists
+}
+
+fn collect_ordered_lists(
+    components: &[jarkup_rs::Component],
+    depth: usize,
+    list_starts: &HashMap<String, ListStart>,
+    ordered_lists: &mut HashMap<String, OrderedList>,
+) {
+    for component in components {
+        let jarkup_rs::Component::BlockComponent(block_component) = component else {
+            continue;
+        };
+
+        let mut child_depth = depth;
+
+        if let jarkup_rs::BlockComponent::List(list) = block_component
+            && let Some(jarkup_rs::ListProps {
+                list_style: Some(jarkup_rs::ListStyle::Ordered),
+            }) = &list.props
+        {
+            if let Some(jarkup_rs::Component::BlockComponent(jarkup_rs::BlockComponent::ListItem(
+                first_item,
+            ))) = list.slots.default.first()
+                && let Some(first_item_id) = &first_item.id
+            {
+                let list_start = list_starts.get(first_item_id).cloned().unwrap_or_default();
+
+                ordered_lists.insert(
+                    first_item_id.clone(),
+                    OrderedList {
+                        start: list_start.start_index.unwrap_or(1),
+                        depth,
+                        format: list_start
+                            .format
+                            .unwrap_or_else(|| NumberingFormat::for_depth(depth)),
+                    },
+                );
+            }
+
+            child_depth += 1;
+        }
+
+        for children in crate::visit::child_slots(block_component) {
+            collect_ordered_lists(children, child_depth, list_starts, ordered_lists);
+        }
+    }
+}
@@ -0,0 +1,506 @@
+/// Options that control how Notion blocks are converted.
+///
+/// The struct is `#[non_exhaustive]` so that new options can be added without
+/// breaking changes. Start from [`ConversionOptions::default`] and set the
+/// fields you need, or deserialize it (missing fields take their defaults):
+///
+/// ```
+/// let mut options = notion_to_jarkup::options::ConversionOptions::default();
+/// options.network.concurrency_limit = 4;
+/// ```
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct ConversionOptions {
+    /// What to do with blocks that have no jarkup counterpart.
+    pub unsupported_block: UnsupportedBlockPolicy,
+
+    /// What to do when a block or its children cannot be fetched or converted.
+    pub on_error: ErrorPolicy,
+
+    /// Bounds on how much of a page is fetched.
+    pub limits: LimitOptions,
+
+    /// Outbound requests made while converting (bookmarks, favicons).
+    pub network: NetworkOptions,
+
+    /// Throttling and retrying of Notion API calls. Read once, when the client is built.
+    pub rate_limit: RateLimitOptions,
+
+    /// How Notion colors are written to `color` / `backgroundColor` props.
+    pub color_theme: crate::color::ColorTheme,
+
+    /// How callouts are typed and whether their icon is emitted.
+    pub callout: CalloutOptions,
+
+    /// How headings with "Toggle heading" enabled are converted.
+    pub toggleable_heading: ToggleableHeadingStyle,
+
+    /// What headings use as their `id`. Applied by [`crate::client::Client::convert`].
+    pub heading_anchor: HeadingAnchorStyle,
+
+    /// How heading levels are shifted or normalised. Applied by
+    /// [`crate::client::Client::convert`], before heading anchors.
+    pub heading_level: HeadingLevelOptions,
+
+    /// Where table captions come from.
+    pub table_caption: TableCaptionSource,
+
+    /// Downloading images to read their dimensions and compute placeholders.
+    pub image_analysis: ImageAnalysisOptions,
+}
+
+/// What to do with blocks that have no jarkup counterpart.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum UnsupportedBlockPolicy {
+    /// Render them as `Unsupported` components.
+    #[default]
+    Render,
+
+    /// Leave them out of the output.
+    Skip,
+}
+
+/// What to do when a block or its children cannot be fetched or converted,
+/// e.g. a synced block whose original is not shared with the integration.
+/// Failures of the page itself always fail the conversion.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ErrorPolicy {
+    /// Fail the whole conversion.
+    #[default]
+    Fail,
+
+    /// Replace the block (or its children, if only they failed) with an
+    /// `Unsupported` component carrying the block id and the error, and
+    /// record the failure in the report.
+    Placeholder,
+
+    /// Leave the block (or its children) out and record the failure in the report.
+    Skip,
+}
+
+/// How headings with "Toggle heading" enabled are converted.
+///
+/// `jarkup_rs::Toggle` has no props and an inline-only summary, so it cannot
+/// carry a heading level itself.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ToggleableHeadingStyle {
+    /// A `Toggle` whose summary is the heading text, with the heading's
+    /// anchor as `id`. Its level is returned in
+    /// [`crate::output::ConversionOutput::toggle_headings`], and it is
+    /// included in `heading_anchor` slugs and `heading_level` adjustments.
+    #[default]
+    Toggle,
+
+    /// A `Heading` (keeping its level and block id as anchor) followed by its
+    /// children, so that the section stays part of the document outline.
+    Heading,
+}
+
+/// What headings use as their `id`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum HeadingAnchorStyle {
+    /// The Notion block id.
+    #[default]
+    BlockId,
+
+    /// A slug of the heading text, unique within the page (`installation`,
+    /// `installation-1`, ...). Links to those headings from within the page
+    /// (`notion.so/...#<block id>`) are rewritten to `#<slug>`.
+    Slug,
+}
+
+/// Where table captions come from. Notion tables have no caption of their own,
+/// so a paragraph directly after the table is used and removed from the output.
+///
+/// ```json
+/// { "source": "prefix", "prefix": "Table:" }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum TableCaptionSource {
+    /// Tables have no caption.
+    #[default]
+    Disabled,
+
+    /// Any paragraph directly after a table.
+    FollowingParagraph,
+
+    /// A paragraph directly after a table that starts with `prefix`.
+    /// The prefix is not part of the caption.
+    Prefix { prefix: String },
+}
+
+/// Image analysis, off by default. Results are returned in
+/// [`crate::output::ConversionOutput::images`]; images that cannot be fetched
+/// or read are left out.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct ImageAnalysisOptions {
+    /// If true, images are downloaded with the client's
+    /// [`crate::image::ImageFetcher`] to read their width and height.
+    /// Notion-hosted images copied to the asset store are analysed from that
+    /// download instead.
+    pub enabled: bool,
+
+    /// The placeholder computed for each image.
+    pub placeholder: ImagePlaceholder,
+
+    /// Images larger than this many bytes are not analysed.
+    pub max_bytes: usize,
+}
+
+impl Default for ImageAnalysisOptions {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            placeholder: ImagePlaceholder::None,
+            max_bytes: 20 * 1024 * 1024,
+        }
+    }
+}
+
+/// A low-quality stand-in shown while an image loads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ImagePlaceholder {
+    #[default]
+    None,
+
+    /// A BlurHash string.
+    Blurhash,
+
+    /// A tiny PNG as a base64 `data:` URL.
+    Lqip,
+}
+
+/// How heading levels are adjusted. Resulting levels are clamped to 1..=6.
+///
+/// ```json
+/// { "adjustment": { "mode": "normalize", "top_level": 2 }, "fix_skips": true }
+/// ```
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct HeadingLevelOptions {
+    pub adjustment: HeadingLevelAdjustment,
+
+    /// If true, skipped levels are closed up (`H1`, `H3`, `H3` becomes
+    /// `H1`, `H2`, `H2`) while keeping the nesting of the outline.
+    pub fix_skips: bool,
+}
+
+/// How all heading levels of a page are moved.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum HeadingLevelAdjustment {
+    /// Levels are kept as in Notion.
+    #[default]
+    Keep,
+
+    /// Every level is moved by `offset` (`1` turns `H1` into `H2`).
+    Shift { offset: i8 },
+
+    /// Levels are moved so that the top level used on the page becomes `top_level`.
+    Normalize { top_level: u8 },
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct NetworkOptions {
+    /// Maximum number of sibling blocks (and links within a rich text) converted
+    /// at the same time, and of downloads (bookmarks, favicons, images,
+    /// assets) in flight across the whole conversion. Values below 1 are
+    /// treated as 1.
+    pub concurrency_limit: usize,
+
+    /// If false, bookmark pages are not fetched and bookmarks only carry their URL.
+    pub fetch_bookmark_metadata: bool,
+
+    /// If false, no favicons are looked up for links.
+    pub fetch_favicons: bool,
+
+    /// If set, favicons up to this many bytes are inlined as `data:` URLs.
+    pub inline_favicon_max_bytes: Option<usize>,
+
+    /// Notion-hosted files larger than this many bytes are not copied to the
+    /// asset store; their block fails with [`crate::error::Error::TooLarge`].
+    pub max_asset_bytes: usize,
+
+    /// The `User-Agent` header sent when fetching bookmarks and favicons.
+    pub user_agent: String,
+}
+
+impl Default for NetworkOptions {
+    fn default() -> Self {
+        Self {
+            concurrency_limit: 8,
+            fetch_bookmark_metadata: true,
+            fetch_favicons: true,
+            inline_favicon_max_bytes: None,
+            max_asset_bytes: 100 * 1024 * 1024,
+            user_agent: String::from("notion-to-jarkup"),
+        }
+    }
+}
+
+/// Bounds on how much of a page is fetched and how long a conversion may take.
+///
+/// Subtrees beyond `max_depth` are replaced by an `Unsupported` placeholder and
+/// listed in [`crate::report::ConversionReport::truncations`]. When the timeout
+/// or a budget is hit (or the conversion is cancelled), pending calls are
+/// abandoned and `on_exceeded` decides what is returned.
+///
+/// ```json
+/// { "timeout_ms": 10000, "max_api_calls": 200, "on_exceeded": "partial" }
+/// ```
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct LimitOptions {
+    /// Maximum nesting depth of fetched blocks; the children of the page are
+    /// at depth 1. `None` means unbounded.
+    pub max_depth: Option<usize>,
+
+    /// Maximum duration of a conversion, including fetching.
+    pub timeout_ms: Option<u64>,
+
+    /// Maximum number of Notion API calls per conversion, counting every page
+    /// of a listing and not counting retries.
+    pub max_api_calls: Option<usize>,
+
+    /// Maximum number of bookmark, favicon, image and asset downloads per conversion.
+    pub max_outbound_requests: Option<usize>,
+
+    pub on_exceeded: LimitPolicy,
+}
+
+impl Default for LimitOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: Some(64),
+            timeout_ms: None,
+            max_api_calls: None,
+            max_outbound_requests: None,
+            on_exceeded: LimitPolicy::default(),
+        }
+    }
+}
+
+/// What a conversion returns when it is cancelled or hits the timeout or a budget.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum LimitPolicy {
+    /// Fail with [`crate::error::Error::Stopped`].
+    #[default]
+    Fail,
+
+    /// Return what was converted so far, with
+    /// [`crate::report::ConversionReport::incomplete`] set.
+    Partial,
+}
+
+/// Throttling and retrying of Notion API calls: the calls of sources whose
+/// [`crate::source::BlockSource::needs_rate_limit`] is `true`, such as
+/// `notionrs::client::Client`. The limits are shared by all conversions of a
+/// client.
+///
+/// ```json
+/// { "requests_per_second": 3.0, "retry": { "max_retries": 5 } }
+/// ```
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct RateLimitOptions {
+    /// Maximum number of calls in flight at the same time. Values below 1 are treated as 1.
+    pub max_concurrent_requests: usize,
+
+    /// Average number of calls started per second (token bucket refill rate).
+    /// Notion allows about 3.
+    pub requests_per_second: f64,
+
+    /// Number of calls that may start at once after an idle period (bucket size).
+    pub burst: u32,
+
+    pub retry: RetryOptions,
+}
+
+impl Default for RateLimitOptions {
+    fn default() -> Self {
+        Self {
+            max_concurrent_requests: 3,
+            requests_per_second: 3.0,
+            burst: 3,
+            retry: RetryOptions::default(),
+        }
+    }
+}
+
+/// Retrying of calls that failed with a rate limit (429), a server error
+/// (5xx) or a network error. Pages of children are retried on their own.
+///
+/// `notionrs` does not expose response headers, so the `Retry-After` of a
+/// Notion 429 is not known and the backoff is used. A custom
+/// [`crate::source::BlockSource`] can return
+/// [`crate::error::Error::RateLimited`] with `retry_after` set to wait that
+/// long instead.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct RetryOptions {
+    /// Retries after the first attempt. 0 disables retrying.
+    pub max_retries: u32,
+
+    /// Delay before the first retry; doubled for every further retry.
+    pub initial_backoff_ms: u64,
+
+    /// Upper bound for the backoff delay.
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryOptions {
+    fn default() -> Self {
+        Self {
+            max_retries: 4,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+        }
+    }
+}
+
+/// How callouts are converted.
+///
+/// The callout type is looked up by icon first, then by color, and defaults
+/// to `note`:
+///
+/// ```json
+/// { "icons": { "💡": "tip", "⚠️": "warning" }, "colors": { "green": "tip" } }
+/// ```
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct CalloutOptions {
+    /// Callout type by icon: an emoji character or a custom emoji name.
+    pub icons: std::collections::HashMap<String, jarkup_rs::CalloutType>,
+
+    /// Callout type by Notion color name. A `<color>_background` color that
+    /// is not listed falls back to `<color>`.
+    pub colors: std::collections::HashMap<String, jarkup_rs::CalloutType>,
+
+    /// If true, the callout icon is emitted at the start of its first paragraph:
+    /// emojis as text, custom emojis and files as an `Icon`. Off by default,
+    /// so that callouts keep the content they had before icons were emitted.
+    pub emit_icon: bool,
+}
+
+impl CalloutOptions {
+    /// Resolves the callout type for a color and an optional icon key.
+    pub fn callout_type(
+        &self,
+        color: notionrs_types::object::color::Color,
+        icon: Option<&str>,
+    ) -> jarkup_rs::CalloutType {
+        let color = color.to_string();
+
+        icon.and_then(|icon| self.icons.get(icon))
+            .or_else(|| self.colors.get(&color))
+            .or_else(|| {
+                color
+                    .strip_suffix("_background")
+                    .and_then(|base| self.colors.get(base))
+            })
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+impl Default for CalloutOptions {
+    fn default() -> Self {
+        use jarkup_rs::CalloutType;
+
+        let colors = [
+            ("default", CalloutType::Note),
+            ("blue", CalloutType::Note),
+            ("gray", CalloutType::Note),
+            ("green", CalloutType::Tip),
+            ("purple", CalloutType::Important),
+            ("yellow", CalloutType::Warning),
+            ("orange", CalloutType::Warning),
+            ("brown", CalloutType::Warning),
+            ("red", CalloutType::Caution),
+            ("pink", CalloutType::Caution),
+        ]
+        .into_iter()
+        .map(|(color, callout_type)| (String::from(color), callout_type))
+        .collect();
+
+        Self {
+            icons: std::collections::HashMap::new(),
+            colors,
+            emit_icon: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notionrs_types::object::color::Color;
+
+    fn callout_options() -> CalloutOptions {
+        let mut options = CalloutOptions::default();
+        options
+            .icons
+            .insert(String::from("💡"), jarkup_rs::CalloutType::Tip);
+        options
+    }
+
+    #[test]
+    fn callout_type_matches_icon_before_color() {
+        let options = callout_options();
+
+        assert!(matches!(
+            options.callout_type(Color::Red, Some("💡")),
+            jarkup_rs::CalloutType::Tip
+        ));
+    }
+
+    #[test]
+    fn callout_type_matches_color() {
+        let options = callout_options();
+
+        assert!(matches!(
+            options.callout_type(Color::Yellow, Some("📌")),
+            jarkup_rs::CalloutType::Warning
+        ));
+        assert!(matches!(
+            options.callout_type(Color::RedBackground, None),
+            jarkup_rs::CalloutType::Caution
+        ));
+    }
+
+    #[test]
+    fn callout_type_falls_back_to_note() {
+        let mut options = callout_options();
+        options.colors.clear();
+
+        assert!(matches!(
+            options.callout_type(Color::Green, Some("📌")),
+            jarkup_rs::CalloutType::Note
+        ));
+    }
+}
@@ -0,0 +1,32 @@
+use std::collections::HashMap;
+
+/// The result of [`crate::client::Client::convert`].
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ConversionOutput {
+    pub components: Vec<jarkup_rs::Component>,
+
+    /// Heading anchors keyed by Notion block id.
+    /// Empty unless `heading_anchor` is [`crate::options::HeadingAnchorStyle::Slug`].
+    pub anchors: HashMap<String, String>,
+
+    /// Numbering of every ordered list, keyed by the block id of its first item.
+    pub ordered_lists: HashMap<String, crate::list::OrderedList>,
+
+    /// Dimensions and placeholders of images, keyed by block id.
+    /// Empty unless image analysis is enabled.
+    pub images: HashMap<String, crate::image::ImageInfo>,
+
+    /// Metadata of bookmarked pages, including what `jarkup_rs::Bookmark`
+    /// cannot carry (favicon, site name, canonical URL), keyed by block id.
+    /// Empty unless `network.fetch_bookmark_metadata` is set; bookmarks whose
+    /// page could not be fetched are left out.
+    pub bookmarks: HashMap<String, crate::bookmark::BookmarkMetadata>,
+
+    /// Levels of toggleable headings converted to `Toggle`s, which have no
+    /// props, keyed by block id. The toggle's `id` is its anchor, like the
+    /// `id` of a heading.
+    pub toggle_headings: HashMap<String, jarkup_rs::HeadingLevel>,
+
+    /// What went wrong or was left out during the conversion.
+    pub report: crate::report::ConversionReport,
+}
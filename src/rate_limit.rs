@@ -0,0 +1,190 @@
+use notionrs_types::object::block::BlockResponse;
+use notionrs_types::object::page::PageResponse;
+use notionrs_types::object::user::User;
+use std::time::{Duration, Instant};
+
+/// A token bucket: calls take a token, tokens refill at a fixed rate up to `burst`.
+#[derive(Debug)]
+struct TokenBucket {
+    state: std::sync::Mutex<TokenBucketState>,
+    requests_per_second: f64,
+    burst: f64,
+}
+
+#[derive(Debug)]
+struct TokenBucketState {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(requests_per_second: f64, burst: u32) -> Self {
+        let burst = f64::from(burst.max(1));
+
+        Self {
+            state: std::sync::Mutex::new(TokenBucketState {
+                tokens: burst,
+                refilled_at: Instant::now(),
+            }),
+            requests_per_second,
+            burst,
+        }
+    }
+
+    async fn acquire(&self) {
+        if !(self.requests_per_second > 0.0 && self.requests_per_second.is_finite()) {
+            return;
+        }
+
+        loop {
+            let wait = {
+                let mut state = self.state.lock().expect("token bucket lock poisoned");
+
+                let now = Instant::now();
+                let elapsed = now.duration_since(state.refilled_at).as_secs_f64();
+                state.tokens = (state.tokens + elapsed * self.requests_per_second).min(self.burst);
+                state.refilled_at = now;
+
+                if state.tokens >= 1.0 {
+                    state.tokens -= 1.0;
+                    return;
+                }
+
+                Duration::from_secs_f64((1.0 - state.tokens) / self.requests_per_second)
+            };
+
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Wraps the client's [`crate::source::BlockSource`] with a concurrency cap,
+/// a token bucket and retries, as configured by
+/// [`crate::options::RateLimitOptions`].
+#[derive(Debug)]
+pub(crate) struct RateLimitedSource {
+    inner: std::sync::Arc<dyn crate::source::BlockSource>,
+    semaphore: tokio::sync::Semaphore,
+    token_bucket: TokenBucket,
+    retry: crate::options::RetryOptions,
+}
+
+impl RateLimitedSource {
+    pub(crate) fn new(
+        inner: std::sync::Arc<dyn crate::source::BlockSource>,
+        options: &crate::options::RateLimitOptions,
+    ) -> Self {
+        Self {
+            inner,
+            semaphore: tokio::sync::Semaphore::new(options.max_concurrent_requests.max(1)),
+            token_bucket: TokenBucket::new(options.requests_per_second, options.burst),
+            retry: options.retry.clone(),
+        }
+    }
+
+    async fn call<T, F, Fut>(&self, request: F) -> Result<T, crate::error::Error>
+    where
+        F: Fn() -> Fut + Send,
+        Fut: std::future::Future<Output = Result<T, crate::error::Error>> + Send,
+    {
+        let mut retries = 0;
+
+        loop {
+            let result = {
+                let _permit = self
+                    .semaphore
+                    .acquire()
+                    .await
+                    .expect("semaphore is never closed");
+                self.token_bucket.acquire().await;
+
+                request().await
+            };
+
+            match result {
+                Err(error) if retries < self.retry.max_retries && is_retryable(&error) => {
+                    let delay = retry_after(&error).unwrap_or_else(|| self.backoff(retries));
+                    tokio::time::sleep(delay).await;
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn backoff(&self, retries: u32) -> Duration {
+        let backoff_ms = self
+            .retry
+            .initial_backoff_ms
+            .saturating_mul(2u64.saturating_pow(retries))
+            .min(self.retry.max_backoff_ms);
+
+        Duration::from_millis(backoff_ms)
+    }
+}
+
+/// Rate limits (429), timeouts, server errors (5xx) and network errors are worth retrying.
+fn is_retryable(error: &crate::error::Error) -> bool {
+    let is_retryable_status = |status: u16| status == 429 || (500..600).contains(&status);
+
+    match error.without_block_path() {
+        crate::error::Error::RateLimited { .. } | crate::error::Error::Timeout(_) => true,
+        crate::error::Error::NotionRs(notionrs::error::Error::Http { status, .. }) => {
+            is_retryable_status(*status)
+        }
+        crate::error::Error::NotionRs(notionrs::error::Error::Network(_)) => true,
+        crate::error::Error::Reqwest(error) => {
+            error.is_connect()
+                || error
+                    .status()
+                    .is_some_and(|status| is_retryable_status(status.as_u16()))
+        }
+        _ => false,
+    }
+}
+
+fn retry_after(error: &crate::error::Error) -> Option<Duration> {
+    match error.without_block_path() {
+        crate::error::Error::RateLimited { retry_after } => *retry_after,
+        _ => None,
+    }
+}
+
+#[async_trait::async_trait]
+impl crate::source::BlockSource for RateLimitedSource {
+    async fn list_block_children(
+        &self,
+        block_id: &str,
+    ) -> Result<Vec<BlockResponse>, crate::error::Error> {
+        self.call(|| self.inner.list_block_children(block_id)).await
+    }
+
+    async fn list_block_children_page(
+        &self,
+        block_id: &str,
+        start_cursor: Option<&str>,
+    ) -> Result<crate::source::BlockChildrenPage, crate::error::Error> {
+        self.call(|| self.inner.list_block_children_page(block_id, start_cursor))
+            .await
+    }
+
+    async fn retrieve_block(&self, block_id: &str) -> Result<BlockResponse, crate::error::Error> {
+        self.call(|| self.inner.retrieve_block(block_id)).await
+    }
+
+    async fn retrieve_page(&self, page_id: &str) -> Result<PageResponse, crate::error::Error> {
+        self.call(|| self.inner.retrieve_page(page_id)).await
+    }
+
+    async fn retrieve_user(&self, user_id: &str) -> Result<User, crate::error::Error> {
+        self.call(|| self.inner.retrieve_user(user_id)).await
+    }
+
+    async fn query_data_source(
+        &self,
+        data_source_id: &str,
+    ) -> Result<Vec<PageResponse>, crate::error::Error> {
+        self.call(|| self.inner.query_data_source(data_source_id))
+            .await
+    }
+}
@@ -0,0 +1,173 @@
+/// What went wrong or was left out during a conversion.
+///
+/// Entries are in the order they were recorded, which may differ from document
+/// order because blocks are converted concurrently.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+#[non_exhaustive]
+pub struct ConversionReport {
+    /// Blocks replaced or left out because of `on_error`.
+    pub failures: Vec<BlockFailure>,
+
+    /// Blocks with no jarkup counterpart, whether rendered or skipped.
+    pub unsupported_blocks: Vec<UnsupportedBlock>,
+
+    /// Mentions that were left out of the rich text.
+    pub dropped_mentions: Vec<DroppedMention>,
+
+    /// Bookmark metadata and favicon requests that failed. The components are
+    /// kept, without the metadata.
+    pub metadata_failures: Vec<MetadataFailure>,
+
+    /// Subtrees that were not fetched because of a limit or a cycle.
+    pub truncations: Vec<Truncation>,
+
+    /// Number of Notion API calls made. Every page of a children listing is a
+    /// call of its own; retries of a call are not counted.
+    pub api_calls: usize,
+
+    /// Number of subtrees reused from the block cache instead of being fetched
+    /// and converted again.
+    pub cache_hits: usize,
+
+    /// Block cache reads and writes that failed. A failed read is treated as a
+    /// miss; a failed write leaves the subtree uncached.
+    pub cache_failures: Vec<CacheFailure>,
+
+    /// Number of bookmark, favicon, image and asset downloads started.
+    pub outbound_requests: usize,
+
+    /// Why the conversion stopped early, if it did. The output is then partial:
+    /// subtrees not fetched in time are listed in `truncations`, and downloads
+    /// not started are left out.
+    pub incomplete: Option<StopReason>,
+
+    /// Wall-clock time of the conversion, including fetching.
+    pub elapsed: std::time::Duration,
+}
+
+/// A block that could not be fetched or converted.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[non_exhaustive]
+pub struct BlockFailure {
+    pub block_id: String,
+    pub message: String,
+}
+
+/// A block with no jarkup counterpart.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[non_exhaustive]
+pub struct UnsupportedBlock {
+    pub block_id: String,
+
+    /// Notion block type, e.g. `SyncedBlock`.
+    pub block_type: String,
+}
+
+/// A mention left out of the rich text of a block.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[non_exhaustive]
+pub struct DroppedMention {
+    pub block_id: String,
+
+    /// Notion mention type, e.g. `page` or `date`.
+    pub mention_type: String,
+
+    pub plain_text: String,
+}
+
+/// A failed request for bookmark metadata or a favicon.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[non_exhaustive]
+pub struct MetadataFailure {
+    pub block_id: String,
+    pub url: String,
+    pub kind: MetadataKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum MetadataKind {
+    Bookmark,
+    Favicon,
+}
+
+/// A failed read or write of the block cache.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[non_exhaustive]
+pub struct CacheFailure {
+    pub block_id: String,
+    pub operation: CacheOperation,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum CacheOperation {
+    Get,
+    Put,
+}
+
+/// A block whose children were not fetched.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[non_exhaustive]
+pub struct Truncation {
+    pub block_id: String,
+    pub reason: TruncationReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum TruncationReason {
+    /// The block is at `limits.max_depth`.
+    DepthLimit,
+
+    /// The block was already fetched during this conversion, e.g. it is its
+    /// own descendant.
+    Cycle,
+
+    /// The conversion stopped before the children were fetched.
+    Stopped,
+}
+
+impl std::fmt::Display for TruncationReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TruncationReason::DepthLimit => write!(f, "maximum depth reached"),
+            TruncationReason::Cycle => write!(f, "cycle detected"),
+            TruncationReason::Stopped => write!(f, "conversion stopped"),
+        }
+    }
+}
+
+/// Why a conversion stopped early.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum StopReason {
+    /// The [`crate::cancel::CancellationToken`] was cancelled.
+    Cancelled,
+
+    /// The deadline of the token, or `limits.timeout_ms`, passed.
+    Deadline,
+
+    /// `limits.max_api_calls` Notion API calls were made.
+    ApiCallBudget,
+
+    /// `limits.max_outbound_requests` downloads were started.
+    OutboundRequestBudget,
+}
+
+impl std::fmt::Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopReason::Cancelled => write!(f, "cancelled"),
+            StopReason::Deadline => write!(f, "deadline exceeded"),
+            StopReason::ApiCallBudget => write!(f, "API call budget exhausted"),
+            StopReason::OutboundRequestBudget => write!(f, "outbound request budget exhausted"),
+        }
+    }
+}
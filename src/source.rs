@@ -0,0 +1,564 @@
+use futures::TryStreamExt;
+use notionrs::PaginateExt;
+use notionrs_types::object::block::BlockResponse;
+use notionrs_types::object::page::PageResponse;
+use notionrs_types::object::user::User;
+use std::collections::HashMap;
+
+/// Where the converter reads Notion data from. Set with
+/// [`crate::client::ClientBuilder::block_source`]; `notionrs::client::Client`
+/// is the default implementation.
+#[async_trait::async_trait]
+pub trait BlockSource: std::fmt::Debug + Send + Sync {
+    /// All children of a block or page, across pages of results.
+    async fn list_block_children(
+        &self,
+        block_id: &str,
+    ) -> Result<Vec<BlockResponse>, crate::error::Error>;
+
+    /// One page of the children of a block or page, starting at
+    /// `start_cursor`. The client rate limits and retries every page on its
+    /// own. By default all children are returned as a single page.
+    async fn list_block_children_page(
+        &self,
+        block_id: &str,
+        start_cursor: Option<&str>,
+    ) -> Result<BlockChildrenPage, crate::error::Error> {
+        let _ = start_cursor;
+
+        Ok(BlockChildrenPage::new(
+            self.list_block_children(block_id).await?,
+            None,
+        ))
+    }
+
+    async fn retrieve_block(&self, block_id: &str) -> Result<BlockResponse, crate::error::Error>;
+
+    async fn retrieve_page(&self, page_id: &str) -> Result<PageResponse, crate::error::Error>;
+
+    async fn retrieve_user(&self, user_id: &str) -> Result<User, crate::error::Error>;
+
+    /// All pages of a database (data source), across pages of results.
+    async fn query_data_source(
+        &self,
+        data_source_id: &str,
+    ) -> Result<Vec<PageResponse>, crate::error::Error>;
+
+    /// Whether calls go to a rate-limited API and should be throttled and
+    /// retried as configured by [`crate::options::RateLimitOptions`]. `true`
+    /// for `notionrs::client::Client`; `false` by default, so that local
+    /// sources are read at full speed.
+    fn needs_rate_limit(&self) -> bool {
+        false
+    }
+}
+
+/// One page of the children of a block, as returned by
+/// [`BlockSource::list_block_children_page`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct BlockChildrenPage {
+    pub results: Vec<BlockResponse>,
+
+    /// The cursor of the next page, or `None` if this is the last one.
+    pub next_cursor: Option<String>,
+}
+
+impl BlockChildrenPage {
+    pub fn new(results: Vec<BlockResponse>, next_cursor: Option<String>) -> Self {
+        Self {
+            results,
+            next_cursor,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl BlockSource for notionrs::client::Client {
+    async fn list_block_children(
+        &self,
+        block_id: &str,
+    ) -> Result<Vec<BlockResponse>, crate::error::Error> {
+        Ok(self
+            .get_block_children()
+            .block_id(block_id)
+            .into_stream()
+            .try_collect()
+            .await?)
+    }
+
+    async fn list_block_children_page(
+        &self,
+        block_id: &str,
+        start_cursor: Option<&str>,
+    ) -> Result<BlockChildrenPage, crate::error::Error> {
+        let request = self.get_block_children().block_id(block_id);
+        let request = match start_cursor {
+            Some(start_cursor) => request.start_cursor(start_cursor),
+            None => request,
+        };
+        let response = request.send().await?;
+
+        // notionrs answers with the cursor it was given rather than the next one.
+        let next_cursor = response
+            .next_cursor
+            .filter(|next_cursor| Some(next_cursor.as_str()) != start_cursor);
+
+        Ok(BlockChildrenPage::new(response.results, next_cursor))
+    }
+
+    fn needs_rate_limit(&self) -> bool {
+        true
+    }
+
+    async fn retrieve_block(&self, block_id: &str) -> Result<BlockResponse, crate::error::Error> {
+        Ok(self.get_block().block_id(block_id).send().await?)
+    }
+
+    async fn retrieve_page(&self, page_id: &str) -> Result<PageResponse, crate::error::Error> {
+        Ok(self.get_page().page_id(page_id).send().await?)
+    }
+
+    async fn retrieve_user(&self, user_id: &str) -> Result<User, crate::error::Error> {
+        Ok(self.get_user().user_id(user_id).send().await?)
+    }
+
+    async fn query_data_source(
+        &self,
+        data_source_id: &str,
+    ) -> Result<Vec<PageResponse>, crate::error::Error> {
+        Ok(self
+            .query_data_source()
+            .data_source_id(data_source_id)
+            .into_stream()
+            .try_collect()
+            .await?)
+    }
+}
+
+/// The kinds of responses a [`BlockSource`] serves, used as directory names
+/// by [`DirectorySource`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum ResponseKind {
+    Block,
+    Children,
+    Page,
+    User,
+    DataSource,
+}
+
+impl ResponseKind {
+    fn directory_name(self) -> &'static str {
+        match self {
+            ResponseKind::Block => "blocks",
+            ResponseKind::Children => "children",
+            ResponseKind::Page => "pages",
+            ResponseKind::User => "users",
+            ResponseKind::DataSource => "data_sources",
+        }
+    }
+}
+
+/// The path of a response in a [`DirectorySource`] layout.
+pub(crate) fn response_path(
+    root: &std::path::Path,
+    kind: ResponseKind,
+    id: &str,
+) -> std::path::PathBuf {
+    root.join(kind.directory_name())
+        .join(format!("{}.json", normalize_id(id)))
+}
+
+/// Serializes a response so that it deserializes again.
+///
+/// `notionrs_types` skips the `type` of block, database and workspace parents
+/// when serializing but requires it when deserializing, so it is restored here.
+pub(crate) fn to_json_value<T: serde::Serialize + ?Sized>(
+    response: &T,
+) -> Result<serde_json::Value, crate::error::Error> {
+    let mut value = serde_json::to_value(response)?;
+    restore_parent_types(&mut value);
+    Ok(value)
+}
+
+fn restore_parent_types(value: &mut serde_json::Value) {
+    const PARENT_TYPES: [&str; 5] = [
+        "data_source_id",
+        "database_id",
+        "page_id",
+        "block_id",
+        "workspace",
+    ];
+
+    match value {
+        serde_json::Value::Array(values) => values.iter_mut().for_each(restore_parent_types),
+        serde_json::Value::Object(object) => {
+            if let Some(serde_json::Value::Object(parent)) = object.get_mut("parent")
+                && !parent.contains_key("type")
+                && let Some(parent_type) = PARENT_TYPES
+                    .iter()
+                    .find(|parent_type| parent.contains_key(**parent_type))
+            {
+                parent.insert(
+                    String::from("type"),
+                    serde_json::Value::String(String::from(*parent_type)),
+                );
+            }
+
+            object.values_mut().for_each(restore_parent_types);
+        }
+        _ => {}
+    }
+}
+
+/// Notion accepts ids with or without dashes; fixtures are keyed without.
+pub(crate) fn normalize_id(id: &str) -> String {
+    id.replace('-', "").to_ascii_lowercase()
+}
+
+/// Serves Notion API responses saved as JSON files, for example by
+/// [`RecordingSource`]:
+///
+/// ```text
+/// blocks/<block id>.json           a block
+/// children/<block id>.json         an array of the block's children
+/// pages/<page id>.json             a page
+/// users/<user id>.json             a user
+/// data_sources/<data source id>.json  an array of the data source's pages
+/// ```
+///
+/// Ids are written without dashes, in lowercase.
+#[derive(Debug, Clone)]
+pub struct DirectorySource {
+    root: std::path::PathBuf,
+}
+
+impl DirectorySource {
+    pub fn new(root: impl Into<std::path::PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    async fn read<T: serde::de::DeserializeOwned>(
+        &self,
+        kind: ResponseKind,
+        id: &str,
+    ) -> Result<T, crate::error::Error> {
+        let path = response_path(&self.root, kind, id);
+
+        let bytes = match tokio::fs::read(&path).await {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Err(crate::error::Error::NotFound(path.display().to_string()));
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+}
+
+#[async_trait::async_trait]
+impl BlockSource for DirectorySource {
+    async fn list_block_children(
+        &self,
+        block_id: &str,
+    ) -> Result<Vec<BlockResponse>, crate::error::Error> {
+        self.read(ResponseKind::Children, block_id).await
+    }
+
+    async fn retrieve_block(&self, block_id: &str) -> Result<BlockResponse, crate::error::Error> {
+        self.read(ResponseKind::Block, block_id).await
+    }
+
+    async fn retrieve_page(&self, page_id: &str) -> Result<PageResponse, crate::error::Error> {
+        self.read(ResponseKind::Page, page_id).await
+    }
+
+    async fn retrieve_user(&self, user_id: &str) -> Result<User, crate::error::Error> {
+        self.read(ResponseKind::User, user_id).await
+    }
+
+    async fn query_data_source(
+        &self,
+        data_source_id: &str,
+    ) -> Result<Vec<PageResponse>, crate::error::Error> {
+        self.read(ResponseKind::DataSource, data_source_id).await
+    }
+}
+
+/// The source of a client built without one, which can only convert trees
+/// that were already fetched. Every call fails with
+/// [`crate::error::Error::Builder`].
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct MissingSource;
+
+impl MissingSource {
+    fn error<T>(&self) -> Result<T, crate::error::Error> {
+        Err(crate::error::Error::Builder(String::from(
+            "`notionrs_client` or `block_source` is not set; only `convert_tree` works without one.",
+        )))
+    }
+}
+
+#[async_trait::async_trait]
+impl BlockSource for MissingSource {
+    async fn list_block_children(
+        &self,
+        _block_id: &str,
+    ) -> Result<Vec<BlockResponse>, crate::error::Error> {
+        self.error()
+    }
+
+    async fn retrieve_block(&self, _block_id: &str) -> Result<BlockResponse, crate::error::Error> {
+        self.error()
+    }
+
+    async fn retrieve_page(&self, _page_id: &str) -> Result<PageResponse, crate::error::Error> {
+        self.error()
+    }
+
+    async fn retrieve_user(&self, _user_id: &str) -> Result<User, crate::error::Error> {
+        self.error()
+    }
+
+    async fn query_data_source(
+        &self,
+        _data_source_id: &str,
+    ) -> Result<Vec<PageResponse>, crate::error::Error> {
+        self.error()
+    }
+}
+
+/// Serves Notion API responses held in memory.
+///
+/// Responses are stored as JSON values because the Notion response types are
+/// not `Clone`.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySource {
+    responses: HashMap<(ResponseKind, String), serde_json::Value>,
+}
+
+impl MemorySource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert_block(&mut self, block: &BlockResponse) {
+        self.insert(ResponseKind::Block, &block.id, block);
+    }
+
+    pub fn insert_children(&mut self, block_id: &str, children: &[BlockResponse]) {
+        self.insert(ResponseKind::Children, block_id, children);
+    }
+
+    pub fn insert_page(&mut self, page: &PageResponse) {
+        self.insert(ResponseKind::Page, &page.id, page);
+    }
+
+    pub fn insert_user(&mut self, user: &User) {
+        self.insert(ResponseKind::User, &user.id, user);
+    }
+
+    pub fn insert_data_source(&mut self, data_source_id: &str, pages: &[PageResponse]) {
+        self.insert(ResponseKind::DataSource, data_source_id, pages);
+    }
+
+    /// Inserts a block tree: every block, and the children of every block.
+    pub fn insert_tree(&mut self, tree: &crate::tree::BlockTree) {
+        self.insert_block(&tree.block);
+        self.insert_trees(&tree.block.id, &tree.children);
+    }
+
+    fn insert_trees(&mut self, block_id: &str, trees: &[crate::tree::BlockTree]) {
+        let children: Vec<&BlockResponse> = trees.iter().map(|tree| &tree.block).collect();
+        self.insert(ResponseKind::Children, block_id, &children);
+
+        for tree in trees {
+            self.insert_tree(tree);
+        }
+    }
+
+    fn insert<T: serde::Serialize + ?Sized>(&mut self, kind: ResponseKind, id: &str, response: &T) {
+        let value = to_json_value(response).expect("Notion API responses serialize to JSON");
+        self.responses.insert((kind, normalize_id(id)), value);
+    }
+
+    fn get<T: serde::de::DeserializeOwned>(
+        &self,
+        kind: ResponseKind,
+        id: &str,
+    ) -> Result<T, crate::error::Error> {
+        let value = self
+            .responses
+            .get(&(kind, normalize_id(id)))
+            .ok_or_else(|| {
+                crate::error::Error::NotFound(format!("{}/{}", kind.directory_name(), id))
+            })?;
+
+        Ok(T::deserialize(value)?)
+    }
+}
+
+#[async_trait::async_trait]
+impl BlockSource for MemorySource {
+    async fn list_block_children(
+        &self,
+        block_id: &str,
+    ) -> Result<Vec<BlockResponse>, crate::error::Error> {
+        self.get(ResponseKind::Children, block_id)
+    }
+
+    async fn retrieve_block(&self, block_id: &str) -> Result<BlockResponse, crate::error::Error> {
+        self.get(ResponseKind::Block, block_id)
+    }
+
+    async fn retrieve_page(&self, page_id: &str) -> Result<PageResponse, crate::error::Error> {
+        self.get(ResponseKind::Page, page_id)
+    }
+
+    async fn retrieve_user(&self, user_id: &str) -> Result<User, crate::error::Error> {
+        self.get(ResponseKind::User, user_id)
+    }
+
+    async fn query_data_source(
+        &self,
+        data_source_id: &str,
+    ) -> Result<Vec<PageResponse>, crate::error::Error> {
+        self.get(ResponseKind::DataSource, data_source_id)
+    }
+}
+
+/// Wraps another source and saves every response it serves in the
+/// [`DirectorySource`] layout, so that a conversion can be replayed offline.
+///
+/// ```no_run
+/// # async fn record() -> Result<(), notion_to_jarkup::error::Error> {
+/// let source = notion_to_jarkup::source::RecordingSource::new(
+///     notionrs::client::Client::new("secret"),
+///     "tests/fixtures/my_page",
+/// );
+///
+/// let client = notion_to_jarkup::client::Client::builder()
+///     .block_source(source)
+///     .build()?;
+///
+/// client.convert("my-page-id").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RecordingSource {
+    inner: std::sync::Arc<dyn BlockSource>,
+    root: std::path::PathBuf,
+
+    /// Children listed page by page, keyed by block id, until the last page.
+    pages: std::sync::Arc<std::sync::Mutex<HashMap<String, Vec<BlockResponse>>>>,
+}
+
+impl RecordingSource {
+    pub fn new(inner: impl BlockSource + 'static, root: impl Into<std::path::PathBuf>) -> Self {
+        Self {
+            inner: std::sync::Arc::new(inner),
+            root: root.into(),
+            pages: std::sync::Arc::default(),
+        }
+    }
+
+    async fn write<T: serde::Serialize + ?Sized>(
+        &self,
+        kind: ResponseKind,
+        id: &str,
+        response: &T,
+    ) -> Result<(), crate::error::Error> {
+        let path = response_path(&self.root, kind, id);
+
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let mut json = serde_json::to_vec_pretty(&to_json_value(response)?)?;
+        json.push(b'\n');
+
+        tokio::fs::write(&path, json).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl BlockSource for RecordingSource {
+    async fn list_block_children(
+        &self,
+        block_id: &str,
+    ) -> Result<Vec<BlockResponse>, crate::error::Error> {
+        let children = self.inner.list_block_children(block_id).await?;
+        self.write(ResponseKind::Children, block_id, &children)
+            .await?;
+        Ok(children)
+    }
+
+    /// Serves the page as is, and saves all children once the last page is served.
+    async fn list_block_children_page(
+        &self,
+        block_id: &str,
+        start_cursor: Option<&str>,
+    ) -> Result<BlockChildrenPage, crate::error::Error> {
+        let page = self
+            .inner
+            .list_block_children_page(block_id, start_cursor)
+            .await?;
+
+        let children = {
+            let mut pages = self.pages.lock().expect("pages lock poisoned");
+            let id = normalize_id(block_id);
+
+            if start_cursor.is_none() {
+                pages.remove(&id);
+            }
+            let serialized = to_json_value(&page.results)?;
+            let children = pages.entry(id.clone()).or_default();
+            children.extend(serde_json::from_value::<Vec<BlockResponse>>(serialized)?);
+
+            if page.next_cursor.is_some() {
+                None
+            } else {
+                pages.remove(&id)
+            }
+        };
+
+        if let Some(children) = children {
+            self.write(ResponseKind::Children, block_id, &children)
+                .await?;
+        }
+
+        Ok(page)
+    }
+
+    async fn retrieve_block(&self, block_id: &str) -> Result<BlockResponse, crate::error::Error> {
+        let block = self.inner.retrieve_block(block_id).await?;
+        self.write(ResponseKind::Block, block_id, &block).await?;
+        Ok(block)
+    }
+
+    async fn retrieve_page(&self, page_id: &str) -> Result<PageResponse, crate::error::Error> {
+        let page = self.inner.retrieve_page(page_id).await?;
+        self.write(ResponseKind::Page, page_id, &page).await?;
+        Ok(page)
+    }
+
+    async fn retrieve_user(&self, user_id: &str) -> Result<User, crate::error::Error> {
+        let user = self.inner.retrieve_user(user_id).await?;
+        self.write(ResponseKind::User, user_id, &user).await?;
+        Ok(user)
+    }
+
+    async fn query_data_source(
+        &self,
+        data_source_id: &str,
+    ) -> Result<Vec<PageResponse>, crate::error::Error> {
+        let pages = self.inner.query_data_source(data_source_id).await?;
+        self.write(ResponseKind::DataSource, data_source_id, &pages)
+            .await?;
+        Ok(pages)
+    }
+}
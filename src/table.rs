@@ -0,0 +1,140 @@
+//! Notion's API does not expose column alignment, so tables carry none.
+
+use crate::options::TableCaptionSource;
+
+/// Marks the cells of a row: every cell of the column header row, and the
+/// first cell of each row when the table has a row header.
+pub(crate) fn mark_header_cells(
+    table_row: &mut jarkup_rs::TableRow,
+    is_column_header: bool,
+    has_row_header: bool,
+) {
+    for (index, cell) in table_row.slots.default.iter_mut().enumerate() {
+        if let jarkup_rs::Component::BlockComponent(jarkup_rs::BlockComponent::TableCell(cell)) =
+            cell
+        {
+            cell.props = Some(jarkup_rs::TableCellProps {
+                is_header: Some(is_column_header || (index == 0 && has_row_header)),
+            });
+        }
+    }
+}
+
+/// Moves the paragraph directly after a table into the table's caption,
+/// as selected by `source`.
+pub(crate) fn attach_captions(
+    components: &mut Vec<jarkup_rs::Component>,
+    source: &TableCaptionSource,
+) {
+    if *source == TableCaptionSource::Disabled {
+        return;
+    }
+
+    let mut index = 0;
+
+    while index + 1 < components.len() {
+        let caption = match &components[index + 1] {
+            jarkup_rs::Component::BlockComponent(jarkup_rs::BlockComponent::Paragraph(
+                paragraph,
+            )) if is_table(&components[index]) => {
+                caption_text(&crate::visit::plain_text(&paragraph.slots.default), source)
+            }
+            _ => None,
+        };
+
+        if let Some(caption) = caption
+            && let jarkup_rs::Component::BlockComponent(jarkup_rs::BlockComponent::Table(table)) =
+                &mut components[index]
+        {
+            table.props.get_or_insert_with(Default::default).caption = Some(caption);
+            components.remove(index + 1);
+        }
+
+        index += 1;
+    }
+}
+
+fn is_table(component: &jarkup_rs::Component) -> bool {
+    matches!(
+        component,
+        jarkup_rs::Component::BlockComponent(jarkup_rs::BlockComponent::Table(_))
+    )
+}
+
+fn caption_text(paragraph_text: &str, source: &TableCaptionSource) -> Option<String> {
+    let caption = match source {
+        TableCaptionSource::Disabled => return None,
+        TableCaptionSource::FollowingParagraph => paragraph_text,
+        TableCaptionSource::Prefix { prefix } => {
+            paragraph_text.trim_start().strip_prefix(prefix)?
+        }
+    }
+    .trim();
+
+    (!caption.is_empty()).then(|| caption.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> jarkup_rs::Component {
+        jarkup_rs::Table::default().into()
+    }
+
+    fn paragraph(text: &str) -> jarkup_rs::Component {
+        jarkup_rs::Paragraph {
+            id: None,
+            props: None,
+            slots: jarkup_rs::ParagraphSlots {
+                default: vec![
+                    jarkup_rs::Text {
+                        id: None,
+                        props: jarkup_rs::TextProps {
+                            text: String::from(text),
+                            ..Default::default()
+                        },
+                        slots: None,
+                    }
+                    .into(),
+                ],
+            },
+        }
+        .into()
+    }
+
+    fn caption(component: &jarkup_rs::Component) -> Option<&str> {
+        match component {
+            jarkup_rs::Component::BlockComponent(jarkup_rs::BlockComponent::Table(table)) => {
+                table.props.as_ref()?.caption.as_deref()
+            }
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn following_paragraph_becomes_the_caption() {
+        let mut components = vec![table(), paragraph(" Sizes "), paragraph("After")];
+
+        attach_captions(&mut components, &TableCaptionSource::FollowingParagraph);
+
+        assert_eq!(components.len(), 2);
+        assert_eq!(caption(&components[0]), Some("Sizes"));
+        assert!(matches!(
+            &components[1],
+            jarkup_rs::Component::BlockComponent(jarkup_rs::BlockComponent::Paragraph(paragraph))
+                if crate::visit::plain_text(&paragraph.slots.default) == "After"
+        ));
+    }
+
+    #[test]
+    fn following_non_paragraph_is_left_alone() {
+        let mut components = vec![table(), table(), paragraph("Sizes")];
+
+        attach_captions(&mut components, &TableCaptionSource::FollowingParagraph);
+
+        assert_eq!(components.len(), 2);
+        assert_eq!(caption(&components[0]), None);
+        assert_eq!(caption(&components[1]), Some("Sizes"));
+    }
+}
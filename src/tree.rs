@@ -0,0 +1,21 @@
+/// A block and all of its descendants, as returned by the Notion API.
+///
+/// Built by [`crate::client::Client::fetch_tree`] and converted by
+/// [`crate::client::Client::convert_tree`]. Trees can be saved as JSON and
+/// converted later without API access.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct BlockTree {
+    pub block: notionrs_types::object::block::BlockResponse,
+
+    #[serde(default)]
+    pub children: Vec<BlockTree>,
+
+    /// Why the children could not be fetched, when `on_error` is not
+    /// [`crate::options::ErrorPolicy::Fail`]. Converted into a placeholder.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub children_error: Option<String>,
+    /// Why the children were not fetched, if a limit or a cycle stopped the
+    /// recursion. Converted into a placeholder.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub truncated: Option<crate::report::TruncationReason>,
+}
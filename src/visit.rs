@@ -0,0 +1,129 @@
+/// Walks a component tree in document order (pre-order).
+///
+/// `on_block` is called for every `Component` before its children are visited,
+/// `on_inline` for every inline component, including those in inline-only slots
+/// (heading text, toggle summaries, table cells, code captions).
+pub(crate) fn walk_mut(
+    components: &mut [jarkup_rs::Component],
+    on_block: &mut dyn FnMut(&mut jarkup_rs::Component),
+    on_inline: &mut dyn FnMut(&mut jarkup_rs::InlineComponent),
+) {
+    for component in components.iter_mut() {
+        on_block(component);
+
+        match component {
+            jarkup_rs::Component::InlineComponent(inline_component) => on_inline(inline_component),
+            jarkup_rs::Component::BlockComponent(block_component) => {
+                walk_block_mut(block_component, on_block, on_inline)
+            }
+        }
+    }
+}
+
+fn walk_block_mut(
+    block_component: &mut jarkup_rs::BlockComponent,
+    on_block: &mut dyn FnMut(&mut jarkup_rs::Component),
+    on_inline: &mut dyn FnMut(&mut jarkup_rs::InlineComponent),
+) {
+    let mut walk_inline = |inline_components: &mut [jarkup_rs::InlineComponent]| {
+        inline_components.iter_mut().for_each(&mut *on_inline);
+    };
+
+    match block_component {
+        jarkup_rs::BlockComponent::Heading(heading) => walk_inline(&mut heading.slots.default),
+        jarkup_rs::BlockComponent::Paragraph(paragraph) => {
+            walk_inline(&mut paragraph.slots.default)
+        }
+        jarkup_rs::BlockComponent::TableCell(table_cell) => {
+            walk_inline(&mut table_cell.slots.default)
+        }
+        jarkup_rs::BlockComponent::CodeBlock(code_block) => {
+            if let Some(slots) = &mut code_block.slots {
+                walk_inline(&mut slots.default);
+            }
+        }
+        jarkup_rs::BlockComponent::Toggle(toggle) => {
+            walk_inline(&mut toggle.slots.summary);
+            walk_mut(&mut toggle.slots.default, on_block, on_inline);
+        }
+        jarkup_rs::BlockComponent::ListItem(list_item) => {
+            walk_mut(&mut list_item.slots.default, on_block, on_inline)
+        }
+        jarkup_rs::BlockComponent::List(list) => {
+            walk_mut(&mut list.slots.default, on_block, on_inline)
+        }
+        jarkup_rs::BlockComponent::BlockQuote(block_quote) => {
+            walk_mut(&mut block_quote.slots.default, on_block, on_inline)
+        }
+        jarkup_rs::BlockComponent::Callout(callout) => {
+            walk_mut(&mut callout.slots.default, on_block, on_inline)
+        }
+        jarkup_rs::BlockComponent::Table(table) => {
+            if let Some(header) = &mut table.slots.header {
+                walk_mut(header, on_block, on_inline);
+            }
+            walk_mut(&mut table.slots.body, on_block, on_inline);
+        }
+        jarkup_rs::BlockComponent::TableRow(table_row) => {
+            walk_mut(&mut table_row.slots.default, on_block, on_inline)
+        }
+        jarkup_rs::BlockComponent::ColumnList(column_list) => {
+            walk_mut(&mut column_list.slots.default, on_block, on_inline)
+        }
+        jarkup_rs::BlockComponent::Column(column) => {
+            walk_mut(&mut column.slots.default, on_block, on_inline)
+        }
+        jarkup_rs::BlockComponent::Divider(_)
+        | jarkup_rs::BlockComponent::Bookmark(_)
+        | jarkup_rs::BlockComponent::File(_)
+        | jarkup_rs::BlockComponent::Image(_)
+        | jarkup_rs::BlockComponent::Katex(_)
+        | jarkup_rs::BlockComponent::Mermaid(_)
+        | jarkup_rs::BlockComponent::Unsupported(_) => {}
+    }
+}
+
+/// Concatenates the text of inline components.
+pub(crate) fn plain_text(inline_components: &[jarkup_rs::InlineComponent]) -> String {
+    inline_components
+        .iter()
+        .filter_map(|inline_component| match inline_component {
+            jarkup_rs::InlineComponent::Text(text) => Some(text.props.text.as_str()),
+            jarkup_rs::InlineComponent::Icon(_) => None,
+        })
+        .collect()
+}
+
+/// The block-level child slots of a block component, in document order.
+pub(crate) fn child_slots(
+    block_component: &jarkup_rs::BlockComponent,
+) -> Vec<&[jarkup_rs::Component]> {
+    match block_component {
+        jarkup_rs::BlockComponent::Toggle(toggle) => vec![&toggle.slots.default],
+        jarkup_rs::BlockComponent::ListItem(list_item) => vec![&list_item.slots.default],
+        jarkup_rs::BlockComponent::List(list) => vec![&list.slots.default],
+        jarkup_rs::BlockComponent::BlockQuote(block_quote) => vec![&block_quote.slots.default],
+        jarkup_rs::BlockComponent::Callout(callout) => vec![&callout.slots.default],
+        jarkup_rs::BlockComponent::Table(table) => table
+            .slots
+            .header
+            .iter()
+            .map(Vec::as_slice)
+            .chain(std::iter::once(table.slots.body.as_slice()))
+            .collect(),
+        jarkup_rs::BlockComponent::TableRow(table_row) => vec![&table_row.slots.default],
+        jarkup_rs::BlockComponent::ColumnList(column_list) => vec![&column_list.slots.default],
+        jarkup_rs::BlockComponent::Column(column) => vec![&column.slots.default],
+        jarkup_rs::BlockComponent::Heading(_)
+        | jarkup_rs::BlockComponent::Paragraph(_)
+        | jarkup_rs::BlockComponent::TableCell(_)
+        | jarkup_rs::BlockComponent::CodeBlock(_)
+        | jarkup_rs::BlockComponent::Divider(_)
+        | jarkup_rs::BlockComponent::Bookmark(_)
+        | jarkup_rs::BlockComponent::File(_)
+        | jarkup_rs::BlockComponent::Image(_)
+        | jarkup_rs::BlockComponent::Katex(_)
+        | jarkup_rs::BlockComponent::Mermaid(_)
+        | jarkup_rs::BlockComponent::Unsupported(_) => Vec::new(),
+    }
+}
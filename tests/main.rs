@@ -1,4 +1,1836 @@
+const BASIC_PAGE_ID: &str = "9c2f4b1e-6a7d-4e0f-8b3a-5c6d7e8f9012";
+
+fn replay_client(
+    fixture: &str,
+    options: notion_to_jarkup::options::ConversionOptions,
+) -> Result<notion_to_jarkup::client::Client, notion_to_jarkup::error::Error> {
+    let source = notion_to_jarkup::source::DirectorySource::new(
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(fixture),
+    );
+
+    notion_to_jarkup::client::Client::builder()
+        .block_source(source)
+        .options(options)
+        .build()
+}
+
+fn offline_options() -> notion_to_jarkup::options::ConversionOptions {
+    let mut options = notion_to_jarkup::options::ConversionOptions::default();
+    options.network.fetch_bookmark_metadata = false;
+    options.network.fetch_favicons = false;
+    options
+}
+
+/// The children of a block of the `basic` fixture, as JSON, to build
+/// variations of them.
+fn basic_children(block_id: &str) -> Vec<serde_json::Value> {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/basic/children")
+        .join(format!("{}.json", block_id.replace('-', "")));
+
+    serde_json::from_slice(&std::fs::read(path).expect("basic fixture")).expect("json")
+}
+
+/// A block of the `basic` fixture, as JSON, to build variations of it.
+fn basic_block(block_id: &str) -> serde_json::Value {
+    basic_children(BASIC_PAGE_ID)
+        .into_iter()
+        .find(|block| block["id"] == block_id)
+        .expect("block in basic fixture")
+}
+
+/// A source serving the given children, keyed by block id.
+fn memory_source(
+    listings: Vec<(&str, Vec<serde_json::Value>)>,
+) -> notion_to_jarkup::source::MemorySource {
+    let mut source = notion_to_jarkup::source::MemorySource::new();
+
+    for (block_id, children) in listings {
+        let children: Vec<notionrs_types::object::block::BlockResponse> =
+            serde_json::from_value(children.into()).expect("blocks");
+        source.insert_children(block_id, &children);
+    }
+
+    source
+}
+
+/// Serves `body` for every request on a local port. Returns the base URL.
+async fn serve(body: Vec<u8>, content_type: &'static str) -> String {
+    serve_with_length(body, content_type, true).await
+}
+
+/// Like [`serve`], optionally without a `Content-Length`, so that the body
+/// ends when the connection is closed.
+async fn serve_with_length(body: Vec<u8>, content_type: &'static str, send_length: bool) -> String {
+    serve_with_status("200 OK", body, content_type, send_length).await
+}
+
+/// Like [`serve_with_length`], answering with `status`, e.g. `404 Not Found`.
+async fn serve_with_status(
+    status: &'static str,
+    body: Vec<u8>,
+    content_type: &'static str,
+    send_length: bool,
+) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind");
+    let address = listener.local_addr().expect("address");
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let body = body.clone();
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                let mut buffer = [0; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match stream.read(&mut buffer).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => request.extend_from_slice(&buffer[..n]),
+                    }
+                }
+                let length = if send_length {
+                    format!("content-length: {}\r\n", body.len())
+                } else {
+                    String::new()
+                };
+                let head = format!(
+                    "HTTP/1.1 {status}\r\ncontent-type: {content_type}\r\n{length}connection: close\r\n\r\n"
+                );
+                let _ = stream.write_all(head.as_bytes()).await;
+                if !request.starts_with(b"HEAD") {
+                    let _ = stream.write_all(&body).await;
+                }
+            });
+        }
+    });
+
+    format!("http://{address}")
+}
+
+#[tokio::test]
+async fn replay_basic() -> Result<(), Box<dyn std::error::Error>> {
+    let client = replay_client("basic", offline_options())?;
+
+    let output = client.convert(BASIC_PAGE_ID).await?;
+    let components = serde_json::to_value(&output.components)?;
+
+    assert_eq!(components[0]["type"], "Heading");
+    assert_eq!(components[0]["props"]["level"], 1);
+
+    let table = &components[6];
+    assert_eq!(table["type"], "Table");
+    assert_eq!(
+        table["slots"]["header"][0]["slots"]["default"][1]["props"]["isHeader"],
+        true
+    );
+    assert_eq!(
+        table["slots"]["body"][0]["slots"]["default"][0]["props"]["isHeader"],
+        true
+    );
+    assert_eq!(
+        table["slots"]["body"][0]["slots"]["default"][1]["props"]["isHeader"],
+        false
+    );
+
+    assert_eq!(components[8]["props"]["alt"], "A diagram");
+    assert_eq!(components[9]["slots"]["default"][1]["props"]["bold"], true);
+    assert_eq!(components[10]["props"]["alt"], "");
+
+    let first_list = &output.ordered_lists["00000003-1111-4222-8333-444455556666"];
+    assert_eq!((first_list.start, first_list.depth), (3, 0));
+
+    let nested_list = &output.ordered_lists["0000001f-1111-4222-8333-444455556666"];
+    assert_eq!(nested_list.depth, 1);
+    assert_eq!(
+        nested_list.format,
+        notion_to_jarkup::list::NumberingFormat::Letters
+    );
+
+    assert_eq!(
+        output.ordered_lists["00000006-1111-4222-8333-444455556666"].start,
+        1
+    );
+
+    assert_eq!(output.report.api_calls, 3);
+    assert!(output.report.failures.is_empty());
+    assert!(output.report.unsupported_blocks.is_empty());
+    assert!(output.report.dropped_mentions.is_empty());
+
+    Ok(())
+}
+
+const COLORS_PAGE_ID: &str = "3c7a1d2e-5b4f-4c6a-9d8e-0f1a2b3c4d5e";
+
+#[tokio::test]
+async fn replay_colors() -> Result<(), Box<dyn std::error::Error>> {
+    let client = replay_client("colors", offline_options())?;
+
+    let output = client.convert(COLORS_PAGE_ID).await?;
+    let components = serde_json::to_value(&output.components)?;
+
+    let heading = &components[0];
+    assert_eq!(heading["slots"]["default"][0]["props"]["color"], "#6987b8");
+
+    let bulleted = &components[1]["slots"]["default"][0]["slots"]["default"][0];
+    assert_eq!(bulleted["props"]["backgroundColor"], "#e8c2c2");
+    assert!(bulleted["props"].get("color").is_none());
+
+    let numbered = &components[2]["slots"]["default"][0]["slots"]["default"][0];
+    assert_eq!(numbered["props"]["color"], "#59b57c");
+
+    let toggle = &components[3];
+    assert_eq!(
+        toggle["slots"]["summary"][0]["props"]["backgroundColor"],
+        "#f0e9d7"
+    );
+
+    let paragraph = &components[4];
+    assert_eq!(paragraph["props"]["color"], "#868e9c");
+
+    let quote = &components[5]["slots"]["default"][0];
+    assert_eq!(quote["props"]["color"], "#9771bd");
+
+    let empty_quote = &components[6];
+    assert_eq!(empty_quote["type"], "BlockQuote");
+    assert_eq!(
+        empty_quote["slots"]["default"][0]["id"],
+        "000000c8-1111-4222-8333-444455556666"
+    );
+    assert_eq!(
+        empty_quote["slots"]["default"][0]["props"]["backgroundColor"],
+        "#c0cce1"
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn replay_basic_with_page_options() -> Result<(), Box<dyn std::error::Error>> {
+    let mut options = offline_options();
+    options.heading_anchor = notion_to_jarkup::options::HeadingAnchorStyle::Slug;
+    options.heading_level.adjustment =
+        notion_to_jarkup::options::HeadingLevelAdjustment::Normalize { top_level: 2 };
+    options.heading_level.fix_skips = true;
+    options.table_caption = notion_to_jarkup::options::TableCaptionSource::Prefix {
+        prefix: String::from("Table:"),
+    };
+
+    let client = replay_client("basic", options)?;
+
+    let output = client.convert(BASIC_PAGE_ID).await?;
+    let components = serde_json::to_value(&output.components)?;
+
+    assert_eq!(components[0]["id"], "getting-started");
+    assert_eq!(components[0]["props"]["level"], 2);
+    assert_eq!(components[5]["id"], "details");
+    assert_eq!(components[5]["props"]["level"], 3);
+    assert_eq!(components[13]["id"], "details-1");
+    assert_eq!(
+        output.anchors["0000000f-1111-4222-8333-444455556666"],
+        "details-1"
+    );
+
+    assert_eq!(
+        components[1]["slots"]["default"][1]["props"]["href"],
+        "#getting-started"
+    );
+
+    assert_eq!(components[6]["props"]["caption"], "Sizes");
+    assert_eq!(components[7]["type"], "Image");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn replay_partial_failure() -> Result<(), Box<dyn std::error::Error>> {
+    // Same page as `basic`, but without the children of the list items.
+    let error = replay_client("partial", offline_options())?
+        .convert(BASIC_PAGE_ID)
+        .await
+        .expect_err("missing children should fail the conversion");
+    assert_eq!(error.block_path().len(), 2);
+    assert_eq!(error.block_path()[0], BASIC_PAGE_ID);
+    assert!(matches!(
+        error.without_block_path(),
+        notion_to_jarkup::error::Error::NotFound(_)
+    ));
+
+    let mut options = offline_options();
+    options.on_error = notion_to_jarkup::options::ErrorPolicy::Placeholder;
+
+    let output = replay_client("partial", options)?
+        .convert(BASIC_PAGE_ID)
+        .await?;
+    let components = serde_json::to_value(&output.components)?;
+
+    let failed_item = &components[2]["slots"]["default"][0];
+    let placeholder = failed_item["slots"]["default"]
+        .as_array()
+        .and_then(|slot| slot.iter().find(|c| c["type"] == "Unsupported"))
+        .ok_or("no placeholder")?;
+    assert_eq!(placeholder["id"], "00000003-1111-4222-8333-444455556666");
+
+    let mut failed_ids: Vec<&str> = output
+        .report
+        .failures
+        .iter()
+        .map(|failure| failure.block_id.as_str())
+        .collect();
+    failed_ids.sort_unstable();
+    assert_eq!(
+        failed_ids,
+        [
+            "00000003-1111-4222-8333-444455556666",
+            "00000008-1111-4222-8333-444455556666"
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn replay_truncated() -> Result<(), Box<dyn std::error::Error>> {
+    let mut options = offline_options();
+    options.limits.max_depth = Some(1);
+
+    let output = replay_client("basic", options)?
+        .convert(BASIC_PAGE_ID)
+        .await?;
+
+    assert_eq!(output.report.api_calls, 1);
+    assert_eq!(output.report.truncations.len(), 2);
+    assert!(output.report.truncations.iter().all(|truncation| {
+        truncation.reason == notion_to_jarkup::report::TruncationReason::DepthLimit
+    }));
+
+    // The list item lists itself as its child.
+    let output = replay_client("cycle", offline_options())?
+        .convert(BASIC_PAGE_ID)
+        .await?;
+    let components = serde_json::to_value(&output.components)?;
+
+    assert_eq!(output.report.api_calls, 2);
+    assert_eq!(
+        output.report.truncations[0].reason,
+        notion_to_jarkup::report::TruncationReason::Cycle
+    );
+
+    let nested_item =
+        &components[0]["slots"]["default"][0]["slots"]["default"][1]["slots"]["default"][0];
+    assert_eq!(nested_item["id"], "00000003-1111-4222-8333-444455556666");
+    let placeholder = nested_item["slots"]["default"]
+        .as_array()
+        .and_then(|slot| slot.iter().find(|c| c["type"] == "Unsupported"))
+        .ok_or("no placeholder")?;
+    assert_eq!(placeholder["id"], "00000003-1111-4222-8333-444455556666");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn replay_with_limits() -> Result<(), Box<dyn std::error::Error>> {
+    let mut options = offline_options();
+    options.limits.max_api_calls = Some(1);
+
+    let error = replay_client("basic", options.clone())?
+        .convert(BASIC_PAGE_ID)
+        .await
+        .expect_err("the call budget should fail the conversion");
+    assert!(matches!(
+        error.without_block_path(),
+        notion_to_jarkup::error::Error::Stopped(
+            notion_to_jarkup::report::StopReason::ApiCallBudget
+        )
+    ));
+
+    options.limits.on_exceeded = notion_to_jarkup::options::LimitPolicy::Partial;
+    let client = replay_client("basic", options)?;
+
+    let output = client.convert(BASIC_PAGE_ID).await?;
+    assert_eq!(
+        output.report.incomplete,
+        Some(notion_to_jarkup::report::StopReason::ApiCallBudget)
+    );
+    assert_eq!(output.report.api_calls, 1);
+    assert_eq!(output.report.truncations.len(), 2);
+    assert!(!output.components.is_empty());
+
+    let cancellation = notion_to_jarkup::cancel::CancellationToken::new();
+    cancellation.cancel();
+
+    let output = client
+        .convert_cancellable(BASIC_PAGE_ID, &cancellation)
+        .await?;
+    assert_eq!(
+        output.report.incomplete,
+        Some(notion_to_jarkup::report::StopReason::Cancelled)
+    );
+    assert!(output.components.is_empty());
+
+    Ok(())
+}
+
+fn cached_client(
+    source: impl notion_to_jarkup::source::BlockSource + 'static,
+    cache_directory: &std::path::Path,
+    options: notion_to_jarkup::options::ConversionOptions,
+) -> Result<notion_to_jarkup::client::Client, notion_to_jarkup::error::Error> {
+    notion_to_jarkup::client::Client::builder()
+        .block_source(source)
+        .options(options)
+        .block_cache(notion_to_jarkup::cache::FilesystemBlockCache::new(
+            cache_directory,
+        ))
+        .build()
+}
+
+#[tokio::test]
+async fn replay_with_block_cache() -> Result<(), Box<dyn std::error::Error>> {
+    let cache_directory =
+        std::env::temp_dir().join(format!("notion-to-jarkup-cache-{}", std::process::id()));
+    let source = || {
+        notion_to_jarkup::source::DirectorySource::new(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/basic"),
+        )
+    };
+
+    let first = cached_client(source(), &cache_directory, offline_options())?
+        .convert(BASIC_PAGE_ID)
+        .await?;
+    assert_eq!((first.report.api_calls, first.report.cache_hits), (3, 0));
+
+    let second = cached_client(source(), &cache_directory, offline_options())?
+        .convert(BASIC_PAGE_ID)
+        .await?;
+    assert_eq!((second.report.api_calls, second.report.cache_hits), (1, 2));
+    assert_eq!(
+        serde_json::to_value(&first.components)?,
+        serde_json::to_value(&second.components)?
+    );
+    assert_eq!(first.ordered_lists, second.ordered_lists);
+
+    // An unreadable entry is a miss, and is replaced.
+    std::fs::write(
+        cache_directory.join("00000003111142228333444455556666.json"),
+        "{",
+    )?;
+
+    let third = cached_client(source(), &cache_directory, offline_options())?
+        .convert(BASIC_PAGE_ID)
+        .await?;
+    assert_eq!((third.report.api_calls, third.report.cache_hits), (2, 1));
+    assert_eq!(third.report.cache_failures.len(), 1);
+    assert_eq!(
+        third.report.cache_failures[0].operation,
+        notion_to_jarkup::report::CacheOperation::Get
+    );
+    assert_eq!(
+        serde_json::to_value(&first.components)?,
+        serde_json::to_value(&third.components)?
+    );
+
+    let fourth = cached_client(source(), &cache_directory, offline_options())?
+        .convert(BASIC_PAGE_ID)
+        .await?;
+    assert_eq!((fourth.report.api_calls, fourth.report.cache_hits), (1, 2));
+    assert!(fourth.report.cache_failures.is_empty());
+
+    std::fs::remove_dir_all(&cache_directory)?;
+
+    Ok(())
+}
+
+/// The `basic` fixture. With `edited_at`, the nested item below the numbered
+/// list item `03` is edited then, which also sets the edit time of `03`.
+fn edited_basic_source(edited_at: Option<&str>) -> notion_to_jarkup::source::MemorySource {
+    const ITEM_ID: &str = "00000003-1111-4222-8333-444455556666";
+    const TABLE_ID: &str = "00000008-1111-4222-8333-444455556666";
+
+    let mut page_children = basic_children(BASIC_PAGE_ID);
+    let mut item_children = basic_children(ITEM_ID);
+
+    if let Some(edited_at) = edited_at {
+        for block in &mut page_children {
+            if block["id"] == ITEM_ID {
+                block["last_edited_time"] = edited_at.into();
+            }
+        }
+
+        let nested_item = &mut item_children[0];
+        nested_item["last_edited_time"] = edited_at.into();
+        let rich_text = &mut nested_item["numbered_list_item"]["rich_text"][0];
+        rich_text["plain_text"] = "Nested, edited".into();
+        rich_text["text"]["content"] = "Nested, edited".into();
+    }
+
+    memory_source(vec![
+        (BASIC_PAGE_ID, page_children),
+        (ITEM_ID, item_children),
+        (TABLE_ID, basic_children(TABLE_ID)),
+    ])
+}
+
+#[tokio::test]
+async fn block_cache_follows_edit_times() -> Result<(), Box<dyn std::error::Error>> {
+    let cache_directory = std::env::temp_dir().join(format!(
+        "notion-to-jarkup-cache-edits-{}",
+        std::process::id()
+    ));
+    let converted_text = |output: &notion_to_jarkup::output::ConversionOutput| {
+        serde_json::to_string(&output.components).expect("json")
+    };
+    let convert = |edited_at, options| {
+        let client = cached_client(edited_basic_source(edited_at), &cache_directory, options);
+        async move { client?.convert(BASIC_PAGE_ID).await }
+    };
+
+    let first = convert(None, offline_options()).await?;
+    assert_eq!((first.report.api_calls, first.report.cache_hits), (3, 0));
+
+    // The nested edit changed the edit time of `03`, so its children are
+    // listed and converted again; the table is reused.
+    let second = convert(Some("2025-03-04T00:00:00Z"), offline_options()).await?;
+    assert_eq!((second.report.api_calls, second.report.cache_hits), (2, 1));
+    assert!(converted_text(&second).contains("Nested, edited"));
+    assert!(second.report.cache_failures.is_empty());
+
+    let third = convert(Some("2025-03-04T00:00:00Z"), offline_options()).await?;
+    assert_eq!((third.report.api_calls, third.report.cache_hits), (1, 2));
+    assert_eq!(converted_text(&second), converted_text(&third));
+
+    // Options that do not change the output keep the cache.
+    let mut options = offline_options();
+    options.network.concurrency_limit = 1;
+    options.limits.timeout_ms = Some(60_000);
+    options.rate_limit.requests_per_second = 1.0;
+    let fourth = convert(Some("2025-03-04T00:00:00Z"), options).await?;
+    assert_eq!((fourth.report.api_calls, fourth.report.cache_hits), (1, 2));
+
+    // Options that do are converted again.
+    let mut options = offline_options();
+    options.table_caption = notion_to_jarkup::options::TableCaptionSource::FollowingParagraph;
+    let fifth = convert(Some("2025-03-04T00:00:00Z"), options).await?;
+    assert_eq!((fifth.report.api_calls, fifth.report.cache_hits), (3, 0));
+
+    std::fs::remove_dir_all(&cache_directory)?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn block_cache_depends_on_the_asset_url() -> Result<(), Box<dyn std::error::Error>> {
+    let cache_directory = std::env::temp_dir().join(format!(
+        "notion-to-jarkup-cache-assets-{}",
+        std::process::id()
+    ));
+    let convert = |public_base_url: &'static str| {
+        let client = notion_to_jarkup::client::Client::builder()
+            .block_source(edited_basic_source(None))
+            .options(offline_options())
+            .block_cache(notion_to_jarkup::cache::FilesystemBlockCache::new(
+                &cache_directory,
+            ))
+            .asset_store(notion_to_jarkup::asset::FilesystemAssetStore::new(
+                cache_directory.join("assets"),
+                public_base_url,
+            ))
+            .build();
+        async move { client?.convert(BASIC_PAGE_ID).await }
+    };
+
+    assert_eq!(convert("/assets").await?.report.cache_hits, 0);
+    assert_eq!(convert("/assets").await?.report.cache_hits, 2);
+    assert_eq!(
+        convert("https://cdn.example.com").await?.report.cache_hits,
+        0
+    );
+
+    std::fs::remove_dir_all(&cache_directory)?;
+
+    Ok(())
+}
+
+#[test]
+fn asset_extension() {
+    let extension = |url, content_type| {
+        notion_to_jarkup::asset::Asset::new("block", url, content_type, b"").extension()
+    };
+
+    assert_eq!(
+        extension("https://s3.example.com/a/Photo.JPG?X-Amz-Signature=1", None),
+        Some(String::from("jpg"))
+    );
+    assert_eq!(
+        extension("https://s3.example.com/a/download", Some("image/png")),
+        Some(String::from("png"))
+    );
+    assert_eq!(
+        extension("https://s3.example.com/a/archive.tar-gz", None),
+        None
+    );
+}
+
+#[tokio::test]
+async fn filesystem_asset_store_deduplicates() -> Result<(), Box<dyn std::error::Error>> {
+    use notion_to_jarkup::asset::AssetStore;
+
+    let directory =
+        std::env::temp_dir().join(format!("notion-to-jarkup-assets-{}", std::process::id()));
+    let store = notion_to_jarkup::asset::FilesystemAssetStore::new(&directory, "/assets/");
+
+    let bytes = b"same image";
+    let asset = |block_id| {
+        notion_to_jarkup::asset::Asset::new(
+            block_id,
+            "https://s3.example.com/a/image.png?X-Amz-Signature=1",
+            Some("image/png"),
+            bytes,
+        )
+    };
+
+    // Identical images on one page are stored concurrently.
+    let urls = futures::future::try_join_all(
+        (0..8).map(|i| store.store(asset(if i % 2 == 0 { "a" } else { "b" }))),
+    )
+    .await?;
+
+    assert!(urls.iter().all(|url| url == &urls[0]));
+    assert!(urls[0].starts_with("/assets/") && urls[0].ends_with(".png"));
+    assert_eq!(std::fs::read_dir(&directory)?.count(), 1);
+
+    std::fs::remove_dir_all(&directory)?;
+
+    Ok(())
+}
+
+/// Logs the URL of every stored asset. Clones share the log.
+#[derive(Debug, Clone, Default)]
+struct RecordingAssetStore {
+    urls: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+#[async_trait::async_trait]
+impl notion_to_jarkup::asset::AssetStore for RecordingAssetStore {
+    async fn store(
+        &self,
+        asset: notion_to_jarkup::asset::Asset<'_>,
+    ) -> Result<String, notion_to_jarkup::error::Error> {
+        self.urls.lock().unwrap().push(asset.url.to_owned());
+        Ok(format!("/assets/{}", asset.content_hash()))
+    }
+}
+
+#[tokio::test]
+async fn notion_hosted_callout_icon_is_stored() -> Result<(), Box<dyn std::error::Error>> {
+    let base_url = serve(b"icon".to_vec(), "image/png").await;
+    let icon_url = format!("{base_url}/icon.png?X-Amz-Signature=1");
+
+    let mut callout = basic_block("0000000c-1111-4222-8333-444455556666");
+    callout["callout"]["icon"] = serde_json::json!({
+        "type": "file",
+        "file": { "url": icon_url, "expiry_time": "2025-01-02T01:00:00.000Z" }
+    });
+
+    let mut options = offline_options();
+    options.callout.emit_icon = true;
+
+    let asset_store = RecordingAssetStore::default();
+
+    let output = notion_to_jarkup::client::Client::builder()
+        .block_source(memory_source(vec![(BASIC_PAGE_ID, vec![callout])]))
+        .options(options)
+        .asset_store(asset_store.clone())
+        .build()?
+        .convert(BASIC_PAGE_ID)
+        .await?;
+    let components = serde_json::to_value(&output.components)?;
+
+    let icon = &components[0]["slots"]["default"][0]["slots"]["default"][0];
+    assert_eq!(icon["type"], "Icon");
+    assert!(
+        icon["props"]["src"]
+            .as_str()
+            .is_some_and(|src| src.starts_with("/assets/"))
+    );
+    assert_eq!(*asset_store.urls.lock().unwrap(), [icon_url]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn oversized_asset_is_a_failure() -> Result<(), Box<dyn std::error::Error>> {
+    let base_url = serve_with_length(vec![0; 64], "image/png", false).await;
+
+    let mut callout = basic_block("0000000c-1111-4222-8333-444455556666");
+    callout["callout"]["icon"] = serde_json::json!({
+        "type": "file",
+        "file": {
+            "url": format!("{base_url}/icon.png"),
+            "expiry_time": "2025-01-02T01:00:00.000Z"
+        }
+    });
+
+    let mut options = offline_options();
+    options.callout.emit_icon = true;
+    options.network.max_asset_bytes = 32;
+    options.on_error = notion_to_jarkup::options::ErrorPolicy::Placeholder;
+
+    let asset_store = RecordingAssetStore::default();
+
+    let output = notion_to_jarkup::client::Client::builder()
+        .block_source(memory_source(vec![(BASIC_PAGE_ID, vec![callout])]))
+        .options(options)
+        .asset_store(asset_store.clone())
+        .build()?
+        .convert(BASIC_PAGE_ID)
+        .await?;
+
+    assert!(asset_store.urls.lock().unwrap().is_empty());
+    assert_eq!(output.report.failures.len(), 1);
+    assert_eq!(
+        output.report.failures[0].block_id,
+        "0000000c-1111-4222-8333-444455556666"
+    );
+    assert!(output.report.failures[0].message.contains("too large"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn bookmark_metadata_is_returned() -> Result<(), Box<dyn std::error::Error>> {
+    let base_url = serve(
+        br#"<html><head>
+            <title>An article</title>
+            <meta property="og:site_name" content="Example">
+            <link rel="icon" href="/icon.png" sizes="32x32">
+        </head></html>"#
+            .to_vec(),
+        "text/html",
+    )
+    .await;
+
+    let mut bookmark = basic_block("0000000e-1111-4222-8333-444455556666");
+    bookmark["bookmark"]["url"] = format!("{base_url}/article").into();
+
+    let mut options = offline_options();
+    options.network.fetch_bookmark_metadata = true;
+
+    let output = notion_to_jarkup::client::Client::builder()
+        .block_source(memory_source(vec![(BASIC_PAGE_ID, vec![bookmark])]))
+        .options(options)
+        .build()?
+        .convert(BASIC_PAGE_ID)
+        .await?;
+
+    let metadata = &output.bookmarks["0000000e-1111-4222-8333-444455556666"];
+    assert_eq!(metadata.title.as_deref(), Some("An article"));
+    assert_eq!(metadata.site_name.as_deref(), Some("Example"));
+    assert_eq!(
+        metadata.favicon.as_deref(),
+        Some(format!("{base_url}/icon.png").as_str())
+    );
+
+    let components = serde_json::to_value(&output.components)?;
+    assert_eq!(components[0]["props"]["title"], "An article");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn bookmark_error_pages_are_not_scraped() -> Result<(), Box<dyn std::error::Error>> {
+    let base_url = serve_with_status(
+        "404 Not Found",
+        b"<html><head><title>404 Not Found</title></head></html>".to_vec(),
+        "text/html",
+        true,
+    )
+    .await;
+
+    let mut bookmark = basic_block("0000000e-1111-4222-8333-444455556666");
+    bookmark["bookmark"]["url"] = format!("{base_url}/missing").into();
+
+    let mut options = offline_options();
+    options.network.fetch_bookmark_metadata = true;
+
+    let output = notion_to_jarkup::client::Client::builder()
+        .block_source(memory_source(vec![(BASIC_PAGE_ID, vec![bookmark])]))
+        .options(options)
+        .build()?
+        .convert(BASIC_PAGE_ID)
+        .await?;
+
+    assert!(output.bookmarks.is_empty());
+    assert_eq!(output.report.metadata_failures.len(), 1);
+    assert_eq!(
+        output.report.metadata_failures[0].kind,
+        notion_to_jarkup::report::MetadataKind::Bookmark
+    );
+
+    let components = serde_json::to_value(&output.components)?;
+    assert_ne!(components[0]["props"]["title"], "404 Not Found");
+
+    Ok(())
+}
+
+#[test]
+fn bookmark_favicon_prefers_icons_close_to_32px() {
+    let cases = [
+        // Closest to 32px wins.
+        (
+            r#"<link rel="icon" href="/16.png" sizes="16x16">
+               <link rel="icon" href="/32.png" sizes="32x32">
+               <link rel="icon" href="/192.png" sizes="192x192">"#,
+            "https://example.com/32.png",
+        ),
+        // The largest of several sizes counts, and `X` is accepted.
+        (
+            r#"<link rel="icon" href="/multi.ico" sizes="16x16 48X48">
+               <link rel="icon" href="/64.png" sizes="64x64">"#,
+            "https://example.com/multi.ico",
+        ),
+        // Downscaling beats upscaling by the same amount.
+        (
+            r#"<link rel="icon" href="/24.png" sizes="24x24">
+               <link rel="icon" href="/56.png" sizes="56x56">"#,
+            "https://example.com/56.png",
+        ),
+        // `any` scales to every size; document order breaks the tie.
+        (
+            r#"<link rel="icon" href="/icon.svg" sizes="any">
+               <link rel="icon" href="/32.png" sizes="32x32">"#,
+            "https://example.com/icon.svg",
+        ),
+        // Unparsable sizes count as unknown, which beats a much too small icon.
+        (
+            r#"<link rel="icon" href="/8.png" sizes="8x8">
+               <link rel="icon" href="/unknown.png" sizes="large">"#,
+            "https://example.com/unknown.png",
+        ),
+        // `apple-touch-icon` is a fallback, unless the icons are much worse.
+        (
+            r#"<link rel="apple-touch-icon" href="/apple.png">
+               <link rel="shortcut icon" href="/favicon.ico">"#,
+            "https://example.com/favicon.ico",
+        ),
+        (
+            r#"<link rel="icon" href="/16.png" sizes="16x16">
+               <link rel="apple-touch-icon" href="/apple.png" sizes="32x32">"#,
+            "https://example.com/apple.png",
+        ),
+        // Links are resolved against `<base href>`, and other schemes are ignored.
+        (
+            r#"<base href="https://cdn.example.com/assets/">
+               <link rel="icon" href="javascript:alert(1)" sizes="32x32">
+               <link rel="icon" href="icon.png">"#,
+            "https://cdn.example.com/assets/icon.png",
+        ),
+    ];
+
+    let page_url = url::Url::parse("https://example.com/article").unwrap();
+
+    for (links, expected) in cases {
+        let html = format!("<html><head>{links}</head></html>");
+        let metadata = notion_to_jarkup::bookmark::BookmarkMetadata::from_html(&html, &page_url);
+
+        assert_eq!(metadata.favicon.as_deref(), Some(expected), "{links}");
+    }
+}
+
+#[tokio::test]
+async fn inline_favicons_respect_max_bytes_without_content_length()
+-> Result<(), Box<dyn std::error::Error>> {
+    let icon_url = format!(
+        "{}/icon.png",
+        serve_with_length(vec![0; 64], "image/png", false).await
+    );
+    let page_url = serve(
+        format!(r#"<html><head><link rel="icon" href="{icon_url}"></head></html>"#).into_bytes(),
+        "text/html",
+    )
+    .await;
+
+    let mut bookmark = basic_block("0000000e-1111-4222-8333-444455556666");
+    bookmark["bookmark"]["url"] = page_url.into();
+
+    for (max_bytes, inlined) in [(64, true), (63, false)] {
+        let mut options = offline_options();
+        options.network.fetch_bookmark_metadata = true;
+        options.network.inline_favicon_max_bytes = Some(max_bytes);
+
+        let output = notion_to_jarkup::client::Client::builder()
+            .block_source(memory_source(vec![(BASIC_PAGE_ID, vec![bookmark.clone()])]))
+            .options(options)
+            .build()?
+            .convert(BASIC_PAGE_ID)
+            .await?;
+
+        let favicon = output.bookmarks["0000000e-1111-4222-8333-444455556666"]
+            .favicon
+            .clone()
+            .unwrap_or_default();
+        if inlined {
+            assert!(favicon.starts_with("data:image/png;base64,"));
+        } else {
+            assert_eq!(favicon, icon_url);
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn toggleable_headings_keep_level_and_anchor() -> Result<(), Box<dyn std::error::Error>> {
+    const TOGGLE_ID: &str = "00000040-1111-4222-8333-444455556666";
+
+    let mut toggle = basic_block("00000007-1111-4222-8333-444455556666");
+    toggle["id"] = TOGGLE_ID.into();
+    toggle["has_children"] = true.into();
+    toggle["heading_3"]["is_toggleable"] = true.into();
+
+    let mut options = offline_options();
+    options.heading_anchor = notion_to_jarkup::options::HeadingAnchorStyle::Slug;
+    options.heading_level.adjustment =
+        notion_to_jarkup::options::HeadingLevelAdjustment::Normalize { top_level: 2 };
+    options.heading_level.fix_skips = true;
+
+    let output = notion_to_jarkup::client::Client::builder()
+        .block_source(memory_source(vec![
+            (
+                BASIC_PAGE_ID,
+                vec![basic_block("00000001-1111-4222-8333-444455556666"), toggle],
+            ),
+            (
+                TOGGLE_ID,
+                vec![basic_block("00000002-1111-4222-8333-444455556666")],
+            ),
+        ]))
+        .options(options)
+        .build()?
+        .convert(BASIC_PAGE_ID)
+        .await?;
+    let components = serde_json::to_value(&output.components)?;
+
+    // H1 becomes H2, and the H3 below it becomes H3 again once the skip is closed.
+    assert_eq!(components[0]["props"]["level"], 2);
+    assert_eq!(u8::from(output.toggle_headings[TOGGLE_ID].clone()), 3);
+
+    assert_eq!(components[1]["type"], "Toggle");
+    assert_eq!(components[1]["id"], "details");
+    assert_eq!(output.anchors[TOGGLE_ID], "details");
+    assert_eq!(components[1]["slots"]["default"][0]["type"], "Paragraph");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn numbered_lists_restart_only_on_different_values() -> Result<(), Box<dyn std::error::Error>>
+{
+    let item = |n: u32, start: Option<u32>, format: Option<&str>| {
+        let mut item = basic_block("00000004-1111-4222-8333-444455556666");
+        item["id"] = format!("{n:08x}-1111-4222-8333-444455556666").into();
+        if let Some(start) = start {
+            item["numbered_list_item"]["list_start_index"] = start.into();
+        }
+        if let Some(format) = format {
+            item["numbered_list_item"]["list_format"] = format.into();
+        }
+        item
+    };
+    let separator = |n: u32| {
+        let mut paragraph = basic_block("00000002-1111-4222-8333-444455556666");
+        paragraph["id"] = format!("{n:08x}-1111-4222-8333-444455556666").into();
+        paragraph
+    };
+
+    let children = vec![
+        // The format repeated on later items, or an item's own number, continue the list.
+        item(0x51, Some(3), Some("numbers")),
+        item(0x52, None, Some("numbers")),
+        item(0x53, None, None),
+        item(0x54, Some(6), None),
+        separator(0x55),
+        // An explicit start repeated on the next item restarts the numbering.
+        item(0x56, Some(5), None),
+        item(0x57, Some(5), None),
+        separator(0x58),
+        item(0x59, Some(5), None),
+        separator(0x5a),
+        // "Start at" on a later item restarts the numbering.
+        item(0x61, None, None),
+        item(0x62, Some(10), None),
+        separator(0x63),
+        // So does a different format.
+        item(0x71, Some(1), Some("letters")),
+        item(0x72, None, Some("roman")),
+    ];
+
+    let output = notion_to_jarkup::client::Client::builder()
+        .block_source(memory_source(vec![(BASIC_PAGE_ID, children)]))
+        .options(offline_options())
+        .build()?
+        .convert(BASIC_PAGE_ID)
+        .await?;
+    let components = serde_json::to_value(&output.components)?;
+
+    let list_lengths: Vec<usize> = components
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|component| component["type"] == "List")
+        .map(|list| list["slots"]["default"].as_array().unwrap().len())
+        .collect();
+    assert_eq!(list_lengths, [4, 1, 1, 1, 1, 1, 1, 1]);
+
+    let start = |n: u32| {
+        let list = &output.ordered_lists[&format!("{n:08x}-1111-4222-8333-444455556666")];
+        (list.start, list.format)
+    };
+    use notion_to_jarkup::list::NumberingFormat;
+    assert_eq!(start(0x51), (3, NumberingFormat::Numbers));
+    assert_eq!(start(0x56), (5, NumberingFormat::Numbers));
+    assert_eq!(start(0x57), (5, NumberingFormat::Numbers));
+    assert_eq!(start(0x59), (5, NumberingFormat::Numbers));
+    assert_eq!(start(0x61), (1, NumberingFormat::Numbers));
+    assert_eq!(start(0x62), (10, NumberingFormat::Numbers));
+    assert_eq!(start(0x71), (1, NumberingFormat::Letters));
+    assert_eq!(start(0x72), (1, NumberingFormat::Roman));
+    assert_eq!(output.ordered_lists.len(), 8);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn pdf_blocks_are_reported_as_unsupported() -> Result<(), Box<dyn std::error::Error>> {
+    let mut pdf = basic_block("0000000d-1111-4222-8333-444455556666");
+    pdf.as_object_mut().unwrap().remove("divider");
+    pdf["type"] = "pdf".into();
+    pdf["pdf"] = serde_json::json!({
+        "type": "external",
+        "external": { "url": "https://example.com/manual.pdf" },
+        "caption": []
+    });
+
+    let output = notion_to_jarkup::client::Client::builder()
+        .block_source(memory_source(vec![(BASIC_PAGE_ID, vec![pdf])]))
+        .options(offline_options())
+        .build()?
+        .convert(BASIC_PAGE_ID)
+        .await?;
+    let components = serde_json::to_value(&output.components)?;
+
+    assert_eq!(components[0]["type"], "Unsupported");
+    assert_eq!(output.report.unsupported_blocks.len(), 1);
+    assert_eq!(output.report.unsupported_blocks[0].block_type, "Pdf");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn callout_icons_are_only_emitted_when_enabled() -> Result<(), Box<dyn std::error::Error>> {
+    for (emit_icon, first_text) in [(false, "Be careful"), (true, "⚠️")] {
+        let mut options = offline_options();
+        options.callout.emit_icon = emit_icon;
+
+        let output = notion_to_jarkup::client::Client::builder()
+            .block_source(memory_source(vec![(
+                BASIC_PAGE_ID,
+                vec![basic_block("0000000c-1111-4222-8333-444455556666")],
+            )]))
+            .options(options)
+            .build()?
+            .convert(BASIC_PAGE_ID)
+            .await?;
+        let components = serde_json::to_value(&output.components)?;
+
+        let paragraph = &components[0]["slots"]["default"][0];
+        assert_eq!(
+            paragraph["slots"]["default"][0]["props"]["text"],
+            first_text
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn image_alt_text_hook_overrides_the_caption() -> Result<(), Box<dyn std::error::Error>> {
+    let output = notion_to_jarkup::client::Client::builder()
+        .block_source(memory_source(vec![(
+            BASIC_PAGE_ID,
+            vec![
+                basic_block("0000000a-1111-4222-8333-444455556666"),
+                basic_block("0000000b-1111-4222-8333-444455556666"),
+            ],
+        )]))
+        .options(offline_options())
+        .image_alt_text(|block_id, src| {
+            if block_id == "0000000a-1111-4222-8333-444455556666" {
+                Some(String::from("A labelled diagram"))
+            } else {
+                src.ends_with("decoration.png")
+                    .then(|| String::from("A decorative border"))
+            }
+        })
+        .build()?
+        .convert(BASIC_PAGE_ID)
+        .await?;
+    let components = serde_json::to_value(&output.components)?;
+
+    assert_eq!(components[0]["props"]["alt"], "A labelled diagram");
+    // The caption itself still follows the image.
+    assert_eq!(components[1]["type"], "Paragraph");
+    assert_eq!(components[2]["props"]["alt"], "A decorative border");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn image_alt_text_hook_can_keep_the_caption() -> Result<(), Box<dyn std::error::Error>> {
+    let output = notion_to_jarkup::client::Client::builder()
+        .block_source(memory_source(vec![(
+            BASIC_PAGE_ID,
+            vec![basic_block("0000000a-1111-4222-8333-444455556666")],
+        )]))
+        .options(offline_options())
+        .image_alt_text(|_, _| None)
+        .build()?
+        .convert(BASIC_PAGE_ID)
+        .await?;
+    let components = serde_json::to_value(&output.components)?;
+
+    assert_eq!(components[0]["props"]["alt"], "A diagram");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn partial_tree_is_marked_as_truncated() -> Result<(), Box<dyn std::error::Error>> {
+    let root = basic_block("00000003-1111-4222-8333-444455556666");
+    let root_id = "00000003-1111-4222-8333-444455556666";
+
+    let mut source = memory_source(vec![(root_id, basic_children(root_id))]);
+    source.insert_block(&serde_json::from_value(root)?);
+
+    // Retrieving the root block spends the whole budget.
+    let mut options = offline_options();
+    options.limits.max_api_calls = Some(1);
+    options.limits.on_exceeded = notion_to_jarkup::options::LimitPolicy::Partial;
+
+    let client = notion_to_jarkup::client::Client::builder()
+        .block_source(source)
+        .options(options)
+        .build()?;
+
+    let tree = client.fetch_tree(root_id).await?;
+    assert!(tree.children.is_empty());
+    assert_eq!(
+        tree.truncated,
+        Some(notion_to_jarkup::report::TruncationReason::Stopped)
+    );
+
+    let output = client.convert_tree(&tree).await?;
+    assert!(output.components.is_empty());
+    assert_eq!(output.report.truncations.len(), 1);
+    assert_eq!(output.report.truncations[0].block_id, root_id);
+
+    // Without a tree to mark, fetching only the children fails instead.
+    let mut options = client.options().clone();
+    options.limits.max_api_calls = Some(0);
+
+    let error = notion_to_jarkup::client::Client::builder()
+        .block_source(memory_source(vec![(root_id, basic_children(root_id))]))
+        .options(options)
+        .build()?
+        .fetch_children(root_id)
+        .await
+        .unwrap_err();
+    assert!(matches!(
+        error.without_block_path(),
+        notion_to_jarkup::error::Error::Stopped(
+            notion_to_jarkup::report::StopReason::ApiCallBudget
+        )
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn convert_tree_needs_no_source() -> Result<(), Box<dyn std::error::Error>> {
+    let client = replay_client("basic", offline_options())?;
+    let expected = client.convert(BASIC_PAGE_ID).await?;
+
+    // The fixture has no block for the page itself, so any block stands in as the root.
+    let tree = notion_to_jarkup::tree::BlockTree {
+        block: serde_json::from_value(basic_block("00000001-1111-4222-8333-444455556666"))?,
+        children: client.fetch_children(BASIC_PAGE_ID).await?,
+        children_error: None,
+        truncated: None,
+    };
+
+    let converter = notion_to_jarkup::client::Client::builder()
+        .options(offline_options())
+        .build()?;
+    let output = converter.convert_tree(&tree).await?;
+
+    assert_eq!(
+        serde_json::to_value(&output.components)?,
+        serde_json::to_value(&expected.components)?
+    );
+
+    let error = converter.convert(BASIC_PAGE_ID).await.unwrap_err();
+    assert!(matches!(
+        error.without_block_path(),
+        notion_to_jarkup::error::Error::Builder(_)
+    ));
+
+    Ok(())
+}
+
+/// Serves a PNG `n` pixels wide and 2 high for `https://example.com/<n>.png`,
+/// earlier images more slowly, and tracks how many fetches are in flight.
+#[derive(Debug, Clone, Default)]
+struct SlowImageFetcher {
+    in_flight: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    max_in_flight: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl notion_to_jarkup::image::ImageFetcher for SlowImageFetcher {
+    async fn fetch(
+        &self,
+        url: &str,
+        _max_bytes: usize,
+    ) -> Result<Option<Vec<u8>>, notion_to_jarkup::error::Error> {
+        use std::sync::atomic::Ordering;
+
+        let width: u32 = url
+            .trim_start_matches("https://example.com/")
+            .trim_end_matches(".png")
+            .parse()
+            .unwrap();
+
+        let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+        tokio::time::sleep(std::time::Duration::from_millis(u64::from(20 - width) * 5)).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+        let mut png = Vec::new();
+        image::RgbaImage::from_pixel(width, 2, image::Rgba([200, 100, 50, 255]))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        Ok(Some(png))
+    }
+}
+
+/// Three list items with three images each, `1.png` to `9.png` in order.
+fn nested_images_source() -> notion_to_jarkup::source::MemorySource {
+    let mut listings = vec![(BASIC_PAGE_ID.to_owned(), Vec::new())];
+
+    for item_index in 0..3 {
+        let mut item = basic_block("00000003-1111-4222-8333-444455556666");
+        let item_id = format!("{:08x}-1111-4222-8333-444455556666", 0x20 + item_index);
+        item["id"] = item_id.clone().into();
+        listings[0].1.push(item);
+
+        let images = (0..3)
+            .map(|image_index| {
+                let n = item_index * 3 + image_index + 1;
+                let mut image = basic_block("0000000b-1111-4222-8333-444455556666");
+                image["id"] = format!("{:08x}-1111-4222-8333-444455556666", 0x30 + n).into();
+                image["image"]["external"]["url"] = format!("https://example.com/{n}.png").into();
+                image
+            })
+            .collect();
+        listings.push((item_id, images));
+    }
+
+    memory_source(
+        listings
+            .iter()
+            .map(|(block_id, children)| (block_id.as_str(), children.clone()))
+            .collect(),
+    )
+}
+
+/// The `src` of every `Image` component, in document order.
+fn image_sources(value: &serde_json::Value) -> Vec<String> {
+    match value {
+        serde_json::Value::Array(values) => values.iter().flat_map(image_sources).collect(),
+        serde_json::Value::Object(object) if object.get("type").is_some_and(|t| t == "Image") => {
+            vec![
+                object["props"]["src"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_owned(),
+            ]
+        }
+        serde_json::Value::Object(object) => object.values().flat_map(image_sources).collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[tokio::test]
+async fn downloads_are_capped_across_the_conversion() -> Result<(), Box<dyn std::error::Error>> {
+    let image_fetcher = SlowImageFetcher::default();
+    let mut options = offline_options();
+    options.network.concurrency_limit = 2;
+    options.image_analysis.enabled = true;
+
+    let output = notion_to_jarkup::client::Client::builder()
+        .block_source(nested_images_source())
+        .options(options)
+        .image_fetcher(image_fetcher.clone())
+        .build()?
+        .convert(BASIC_PAGE_ID)
+        .await?;
+
+    // Two items with two images each would be four downloads without the shared cap.
+    assert_eq!(
+        image_fetcher
+            .max_in_flight
+            .load(std::sync::atomic::Ordering::SeqCst),
+        2
+    );
+
+    let expected: Vec<String> = (1..=9)
+        .map(|n| format!("https://example.com/{n}.png"))
+        .collect();
+    assert_eq!(
+        image_sources(&serde_json::to_value(&output.components)?),
+        expected
+    );
+    assert_eq!(output.images.len(), 9);
+    for n in 1..=9u32 {
+        let block_id = format!("{:08x}-1111-4222-8333-444455556666", 0x30 + n);
+        assert_eq!(output.images[&block_id].width, n);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn image_analysis_reads_size_and_placeholder() -> Result<(), Box<dyn std::error::Error>> {
+    let mut image = basic_block("0000000b-1111-4222-8333-444455556666");
+    image["image"]["external"]["url"] = "https://example.com/7.png".into();
+
+    for placeholder in [
+        notion_to_jarkup::options::ImagePlaceholder::Blurhash,
+        notion_to_jarkup::options::ImagePlaceholder::Lqip,
+    ] {
+        let mut options = offline_options();
+        options.image_analysis.enabled = true;
+        options.image_analysis.placeholder = placeholder;
+
+        let output = notion_to_jarkup::client::Client::builder()
+            .block_source(memory_source(vec![(BASIC_PAGE_ID, vec![image.clone()])]))
+            .options(options)
+            .image_fetcher(SlowImageFetcher::default())
+            .build()?
+            .convert(BASIC_PAGE_ID)
+            .await?;
+
+        let info = &output.images["0000000b-1111-4222-8333-444455556666"];
+        assert_eq!((info.width, info.height), (7, 2));
+        match placeholder {
+            notion_to_jarkup::options::ImagePlaceholder::Blurhash => {
+                assert!(info.blurhash.is_some());
+                assert!(info.lqip.is_none());
+            }
+            _ => {
+                assert!(info.blurhash.is_none());
+                assert!(
+                    info.lqip
+                        .as_deref()
+                        .is_some_and(|lqip| lqip.starts_with("data:image/png;base64,"))
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn image_analysis_skips_images_over_max_bytes() -> Result<(), Box<dyn std::error::Error>> {
+    let mut png = Vec::new();
+    image::RgbaImage::from_pixel(64, 64, image::Rgba([0, 0, 0, 255]))
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)?;
+
+    for send_length in [true, false] {
+        let base_url = serve_with_length(png.clone(), "image/png", send_length).await;
+
+        let mut image = basic_block("0000000b-1111-4222-8333-444455556666");
+        image["image"]["external"]["url"] = format!("{base_url}/image.png").into();
+
+        let convert = |max_bytes| {
+            let mut options = offline_options();
+            options.image_analysis.enabled = true;
+            options.image_analysis.max_bytes = max_bytes;
+
+            let client = notion_to_jarkup::client::Client::builder()
+                .block_source(memory_source(vec![(BASIC_PAGE_ID, vec![image.clone()])]))
+                .options(options)
+                .build();
+
+            async move { client?.convert(BASIC_PAGE_ID).await }
+        };
+
+        assert_eq!(convert(png.len()).await?.images.len(), 1);
+        assert!(convert(png.len() - 1).await?.images.is_empty());
+    }
+
+    Ok(())
+}
+
+/// Serves the same bytes for every URL and counts the fetches. Clones share
+/// the count.
+#[derive(Debug, Clone, Default)]
+struct StaticImageFetcher {
+    bytes: Vec<u8>,
+    fetches: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+}
+
+#[async_trait::async_trait]
+impl notion_to_jarkup::image::ImageFetcher for StaticImageFetcher {
+    async fn fetch(
+        &self,
+        _url: &str,
+        _max_bytes: usize,
+    ) -> Result<Option<Vec<u8>>, notion_to_jarkup::error::Error> {
+        self.fetches
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        Ok(Some(self.bytes.clone()))
+    }
+}
+
+fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut png = Vec::new();
+    image::RgbaImage::from_pixel(width, height, image::Rgba([200, 100, 50, 255]))
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+    png
+}
+
+#[tokio::test]
+async fn stored_image_is_analysed_without_a_second_download()
+-> Result<(), Box<dyn std::error::Error>> {
+    let base_url = serve(png(5, 3), "image/png").await;
+    let image_url = format!("{base_url}/image.png?X-Amz-Signature=1");
+
+    let mut image = basic_block("0000000b-1111-4222-8333-444455556666");
+    image["image"] = serde_json::json!({
+        "type": "file",
+        "file": { "url": image_url, "expiry_time": "2025-01-02T01:00:00.000Z" },
+        "caption": []
+    });
+
+    let mut options = offline_options();
+    options.image_analysis.enabled = true;
+    options.image_analysis.placeholder = notion_to_jarkup::options::ImagePlaceholder::Blurhash;
+
+    let asset_store = RecordingAssetStore::default();
+    let image_fetcher = StaticImageFetcher::default();
+
+    let output = notion_to_jarkup::client::Client::builder()
+        .block_source(memory_source(vec![(BASIC_PAGE_ID, vec![image])]))
+        .options(options)
+        .asset_store(asset_store.clone())
+        .image_fetcher(image_fetcher.clone())
+        .build()?
+        .convert(BASIC_PAGE_ID)
+        .await?;
+
+    assert_eq!(*asset_store.urls.lock().unwrap(), [image_url]);
+    assert_eq!(
+        image_fetcher
+            .fetches
+            .load(std::sync::atomic::Ordering::SeqCst),
+        0
+    );
+    assert_eq!(output.report.outbound_requests, 1);
+
+    let info = &output.images["0000000b-1111-4222-8333-444455556666"];
+    assert_eq!((info.width, info.height), (5, 3));
+    assert!(info.blurhash.is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn huge_declared_dimensions_are_not_decoded() -> Result<(), Box<dyn std::error::Error>> {
+    // A 1x1 PNG whose header claims 50000x50000 pixels, with the header CRC fixed up.
+    let mut bytes = png(1, 1);
+    bytes[16..20].copy_from_slice(&50_000u32.to_be_bytes());
+    bytes[20..24].copy_from_slice(&50_000u32.to_be_bytes());
+    let crc = crc32(&bytes[12..29]);
+    bytes[29..33].copy_from_slice(&crc.to_be_bytes());
+
+    let mut image = basic_block("0000000b-1111-4222-8333-444455556666");
+    image["image"]["external"]["url"] = "https://example.com/huge.png".into();
+
+    for placeholder in [
+        notion_to_jarkup::options::ImagePlaceholder::Blurhash,
+        notion_to_jarkup::options::ImagePlaceholder::Lqip,
+    ] {
+        let mut options = offline_options();
+        options.image_analysis.enabled = true;
+        options.image_analysis.placeholder = placeholder;
+
+        let output = notion_to_jarkup::client::Client::builder()
+            .block_source(memory_source(vec![(BASIC_PAGE_ID, vec![image.clone()])]))
+            .options(options)
+            .image_fetcher(StaticImageFetcher {
+                bytes: bytes.clone(),
+                ..StaticImageFetcher::default()
+            })
+            .build()?
+            .convert(BASIC_PAGE_ID)
+            .await?;
+
+        let info = &output.images["0000000b-1111-4222-8333-444455556666"];
+        assert_eq!((info.width, info.height), (50_000, 50_000));
+        assert!(info.blurhash.is_none());
+        assert!(info.lqip.is_none());
+    }
+
+    Ok(())
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Serves the children of the page one block per page, failing requests as
+/// told, and logs the page index of every request. Clones share the log.
+/// Rate limited like the Notion API it stands in for.
+#[derive(Debug, Clone)]
+struct PagedSource {
+    children: Vec<serde_json::Value>,
+    failures: std::sync::Arc<std::sync::Mutex<Vec<(usize, notion_to_jarkup::error::Error)>>>,
+    requests: std::sync::Arc<std::sync::Mutex<Vec<(usize, std::time::Instant)>>>,
+}
+
+impl PagedSource {
+    fn new(pages: usize, failures: Vec<(usize, notion_to_jarkup::error::Error)>) -> Self {
+        let children = (0..pages)
+            .map(|index| {
+                let mut block = basic_block("00000002-1111-4222-8333-444455556666");
+                block["id"] = format!("{:08x}-1111-4222-8333-444455556666", 0xf0 + index).into();
+                block
+            })
+            .collect();
+
+        Self {
+            children,
+            failures: std::sync::Arc::new(std::sync::Mutex::new(failures)),
+            requests: std::sync::Arc::default(),
+        }
+    }
+
+    fn requested_pages(&self) -> Vec<usize> {
+        let requests = self.requests.lock().unwrap();
+        requests.iter().map(|(index, _)| *index).collect()
+    }
+}
+
+#[async_trait::async_trait]
+impl notion_to_jarkup::source::BlockSource for PagedSource {
+    async fn list_block_children(
+        &self,
+        _block_id: &str,
+    ) -> Result<Vec<notionrs_types::object::block::BlockResponse>, notion_to_jarkup::error::Error>
+    {
+        Ok(serde_json::from_value(self.children.clone().into())?)
+    }
+
+    async fn list_block_children_page(
+        &self,
+        _block_id: &str,
+        start_cursor: Option<&str>,
+    ) -> Result<notion_to_jarkup::source::BlockChildrenPage, notion_to_jarkup::error::Error> {
+        let index = start_cursor.map_or(0, |cursor| cursor.parse().unwrap());
+        self.requests
+            .lock()
+            .unwrap()
+            .push((index, std::time::Instant::now()));
+
+        {
+            let mut failures = self.failures.lock().unwrap();
+            if let Some(position) = failures.iter().position(|(page, _)| *page == index) {
+                return Err(failures.remove(position).1);
+            }
+        }
+
+        let next_cursor = (index + 1 < self.children.len()).then(|| (index + 1).to_string());
+        Ok(notion_to_jarkup::source::BlockChildrenPage::new(
+            vec![serde_json::from_value(self.children[index].clone())?],
+            next_cursor,
+        ))
+    }
+
+    async fn retrieve_block(
+        &self,
+        block_id: &str,
+    ) -> Result<notionrs_types::object::block::BlockResponse, notion_to_jarkup::error::Error> {
+        Err(notion_to_jarkup::error::Error::NotFound(
+            block_id.to_owned(),
+        ))
+    }
+
+    async fn retrieve_page(
+        &self,
+        page_id: &str,
+    ) -> Result<notionrs_types::object::page::PageResponse, notion_to_jarkup::error::Error> {
+        Err(notion_to_jarkup::error::Error::NotFound(page_id.to_owned()))
+    }
+
+    async fn retrieve_user(
+        &self,
+        user_id: &str,
+    ) -> Result<notionrs_types::object::user::User, notion_to_jarkup::error::Error> {
+        Err(notion_to_jarkup::error::Error::NotFound(user_id.to_owned()))
+    }
+
+    async fn query_data_source(
+        &self,
+        data_source_id: &str,
+    ) -> Result<Vec<notionrs_types::object::page::PageResponse>, notion_to_jarkup::error::Error>
+    {
+        Err(notion_to_jarkup::error::Error::NotFound(
+            data_source_id.to_owned(),
+        ))
+    }
+
+    fn needs_rate_limit(&self) -> bool {
+        true
+    }
+}
+
+/// Options with short backoffs.
+fn short_backoff_options() -> notion_to_jarkup::options::ConversionOptions {
+    let mut options = offline_options();
+    options.rate_limit.retry.initial_backoff_ms = 1;
+    options.rate_limit.retry.max_backoff_ms = 1;
+    options
+}
+
+async fn convert_paged(
+    source: &PagedSource,
+    options: notion_to_jarkup::options::ConversionOptions,
+) -> Result<notion_to_jarkup::output::ConversionOutput, notion_to_jarkup::error::Error> {
+    notion_to_jarkup::client::Client::builder()
+        .block_source(source.clone())
+        .options(options)
+        .build()?
+        .convert(BASIC_PAGE_ID)
+        .await
+}
+
+fn http_error(status: u16) -> notion_to_jarkup::error::Error {
+    notionrs::error::Error::Http {
+        status,
+        message: String::new(),
+    }
+    .into()
+}
+
+#[tokio::test]
+async fn paged_listing_retries_only_the_failed_page() -> Result<(), Box<dyn std::error::Error>> {
+    let source = PagedSource::new(
+        3,
+        vec![
+            (
+                1,
+                notion_to_jarkup::error::Error::RateLimited { retry_after: None },
+            ),
+            (2, http_error(503)),
+            (2, notion_to_jarkup::error::Error::Timeout(String::new())),
+        ],
+    );
+
+    let output = convert_paged(&source, short_backoff_options()).await?;
+
+    assert_eq!(output.components.len(), 3);
+    assert_eq!(source.requested_pages(), [0, 1, 1, 2, 2, 2]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn paged_listing_does_not_retry_client_errors() {
+    let errors = [
+        notion_to_jarkup::error::Error::NotFound(String::new()),
+        notion_to_jarkup::error::Error::Unauthorized(String::new()),
+        notion_to_jarkup::error::Error::Restricted(String::new()),
+        notion_to_jarkup::error::Error::Validation(String::new()),
+        http_error(409),
+    ];
+
+    for error in errors {
+        let source = PagedSource::new(3, vec![(1, error)]);
+
+        assert!(
+            convert_paged(&source, short_backoff_options())
+                .await
+                .is_err()
+        );
+        assert_eq!(source.requested_pages(), [0, 1]);
+    }
+}
+
 #[tokio::test]
+async fn paged_listing_gives_up_after_max_retries() {
+    let source = PagedSource::new(
+        1,
+        (0..3)
+            .map(|_| (0, notion_to_jarkup::error::Error::Timeout(String::new())))
+            .collect(),
+    );
+    let mut options = short_backoff_options();
+    options.rate_limit.retry.max_retries = 2;
+
+    let error = convert_paged(&source, options).await.unwrap_err();
+
+    assert!(matches!(
+        error.without_block_path(),
+        notion_to_jarkup::error::Error::Timeout(_)
+    ));
+    assert_eq!(source.requested_pages(), [0, 0, 0]);
+}
+
+#[tokio::test]
+async fn retries_back_off_exponentially() -> Result<(), Box<dyn std::error::Error>> {
+    let source = PagedSource::new(1, (0..3).map(|_| (0, http_error(500))).collect());
+    let mut options = short_backoff_options();
+    options.rate_limit.retry.initial_backoff_ms = 40;
+    options.rate_limit.retry.max_backoff_ms = 60;
+
+    convert_paged(&source, options).await?;
+
+    // 40ms, then 80ms capped to 60ms, twice.
+    let requests = source.requests.lock().unwrap();
+    let delays: Vec<_> = requests
+        .windows(2)
+        .map(|pair| pair[1].1 - pair[0].1)
+        .collect();
+    assert_eq!(delays.len(), 3);
+    assert!(delays[0] >= std::time::Duration::from_millis(40));
+    assert!(delays[1] >= std::time::Duration::from_millis(60));
+    assert!(delays[2] >= std::time::Duration::from_millis(60));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn retry_after_replaces_the_backoff() -> Result<(), Box<dyn std::error::Error>> {
+    let source = PagedSource::new(
+        1,
+        vec![(
+            0,
+            notion_to_jarkup::error::Error::RateLimited {
+                retry_after: Some(std::time::Duration::from_millis(100)),
+            },
+        )],
+    );
+
+    convert_paged(&source, short_backoff_options()).await?;
+
+    let requests = source.requests.lock().unwrap();
+    assert!(requests[1].1 - requests[0].1 >= std::time::Duration::from_millis(100));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn token_bucket_throttles_every_page() -> Result<(), Box<dyn std::error::Error>> {
+    let source = PagedSource::new(5, Vec::new());
+    let mut options = short_backoff_options();
+    options.rate_limit.requests_per_second = 20.0;
+    options.rate_limit.burst = 2;
+
+    convert_paged(&source, options).await?;
+
+    // The burst is spent on the first two pages, then one page per 50ms.
+    let requests = source.requests.lock().unwrap();
+    let started = requests[0].1;
+    assert_eq!(requests.len(), 5);
+    for (index, (_, requested_at)) in requests.iter().enumerate().skip(2) {
+        let expected = std::time::Duration::from_millis(50 * (index as u64 - 1));
+        assert!(*requested_at - started >= expected - std::time::Duration::from_millis(5));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn paged_listing_counts_every_page() -> Result<(), Box<dyn std::error::Error>> {
+    let source = PagedSource::new(4, Vec::new());
+
+    let output = convert_paged(&source, short_backoff_options()).await?;
+    assert_eq!(output.report.api_calls, 4);
+
+    let source = PagedSource::new(4, Vec::new());
+    let mut options = short_backoff_options();
+    options.limits.max_api_calls = Some(2);
+
+    let error = convert_paged(&source, options).await.unwrap_err();
+    assert!(matches!(
+        error.without_block_path(),
+        notion_to_jarkup::error::Error::Stopped(
+            notion_to_jarkup::report::StopReason::ApiCallBudget
+        )
+    ));
+    assert_eq!(source.requested_pages(), [0, 1]);
+
+    Ok(())
+}
+
+/// Records the responses for `BLOCK_ID` into `tests/fixtures/$FIXTURE_NAME`.
+///
+/// `cargo test --test main record -- --ignored`
+#[tokio::test]
+#[ignore = "needs NOTION_API_KEY, BLOCK_ID and FIXTURE_NAME"]
+async fn record() -> Result<(), Box<dyn std::error::Error>> {
+    dotenvy::dotenv().ok();
+    let notion_api_key = std::env::var("NOTION_API_KEY")?;
+    let block_id = std::env::var("BLOCK_ID")?;
+    let fixture_name = std::env::var("FIXTURE_NAME")?;
+
+    let source = notion_to_jarkup::source::RecordingSource::new(
+        notionrs::client::Client::new(notion_api_key),
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(fixture_name),
+    );
+
+    let client = notion_to_jarkup::client::Client::builder()
+        .block_source(source)
+        .options(offline_options())
+        .build()?;
+
+    client.convert(&block_id).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "needs NOTION_API_KEY and BLOCK_ID"]
 async fn convert() -> Result<(), Box<dyn std::error::Error>> {
     dotenvy::dotenv().ok();
     let notion_api_key = std::env::var("NOTION_API_KEY")?;
@@ -7,11 +1839,11 @@ async fn convert() -> Result<(), Box<dyn std::error::Error>> {
     let notionrs_client = notionrs::client::Client::new(notion_api_key);
     let reqwest_client = reqwest::Client::new();
 
-    let client = notion_to_jarkup::client::Client {
-        notionrs_client,
-        reqwest_client,
-        enable_unsupported_block: true,
-    };
+    let client = notion_to_jarkup::client::Client::builder()
+        .notionrs_client(notionrs_client)
+        .reqwest_client(reqwest_client)
+        .unsupported_block(notion_to_jarkup::options::UnsupportedBlockPolicy::Render)
+        .build()?;
 
     let result = client.convert_block(&block_id).await?;
 